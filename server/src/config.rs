@@ -3,12 +3,86 @@ use ::serde::Deserialize;
 use postgres_protocol::escape::escape_identifier;
 use std::lazy::SyncLazy;
 
+#[derive(Deserialize, Default)]
+pub struct CrontabConfig {
+  pub file: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BulkConfig {
+  pub limit: i64,
+}
+
+#[derive(Deserialize)]
+pub struct ArchiveConfig {
+  pub enabled: bool,
+}
+
+#[derive(Deserialize)]
+pub struct SamplerConfig {
+  pub enabled: bool,
+  /// Seconds between two samples of the job counts
+  pub interval: u64,
+  /// Days samples are kept at full resolution, before being averaged by hour
+  pub retention: i32,
+  /// Days hourly samples are kept
+  pub history: i32,
+}
+
+#[derive(Deserialize)]
+pub struct PauseConfig {
+  /// Seconds between two checks that paused job queues are still held back
+  pub interval: u64,
+}
+
+#[derive(Deserialize)]
+pub struct UnlockConfig {
+  /// Seconds a lock must be held before it may be cleared, which should exceed
+  /// the longest expected job duration
+  pub minage: u32,
+}
+
+#[derive(Deserialize)]
+pub struct ExportConfig {
+  /// Exports streamed at once, each holding a connection outside of the pool
+  pub concurrency: usize,
+}
+
+#[derive(Deserialize)]
+pub struct AuthConfig {
+  pub enabled: bool,
+  /// Comma separated sha256 hex digests of tokens accepted besides the stored
+  /// ones
+  pub tokens: Option<String>,
+}
+
+impl AuthConfig {
+  pub fn token_hashes(&self) -> impl Iterator<Item = &str> {
+    self
+      .tokens
+      .iter()
+      .flat_map(|tokens| tokens.split(','))
+      .map(str::trim)
+      .filter(|token_hash| !token_hash.is_empty())
+  }
+}
+
 #[derive(Deserialize)]
 pub struct Config {
   pub port: String,
   pub host: String,
   pub graphile_worker_schema: String,
+  pub graphboard_schema: String,
+  #[serde(default)]
+  pub crontab: CrontabConfig,
   pub pg: deadpool_postgres::Config,
+  pub bulk: BulkConfig,
+  pub auth: AuthConfig,
+  pub archive: ArchiveConfig,
+  pub sampler: SamplerConfig,
+  pub pause: PauseConfig,
+  pub unlock: UnlockConfig,
+  pub export: ExportConfig,
 }
 
 impl Config {
@@ -18,12 +92,27 @@ impl Config {
       .set_default("port", 80)?
       .set_default("host", "0.0.0.0")?
       .set_default("graphile_worker_schema", String::from("graphile_worker"))?
+      .set_default("graphboard_schema", String::from("graphboard"))?
       .set_default("pg.pool.max_size", 16)?
+      .set_default("bulk.limit", 1000)?
+      .set_default("auth.enabled", true)?
+      .set_default("archive.enabled", false)?
+      .set_default("sampler.enabled", true)?
+      .set_default("sampler.interval", 60)?
+      .set_default("sampler.retention", 7)?
+      .set_default("sampler.history", 180)?
+      .set_default("pause.interval", 60)?
+      .set_default("unlock.minage", 300)?
+      .set_default("export.concurrency", 4)?
       .merge(::config::Environment::new().separator("_"))?;
     cfg.set(
       "graphile_worker_schema",
       escape_identifier(cfg.get::<String>("graphile_worker_schema")?.as_str()),
     )?;
+    cfg.set(
+      "graphboard_schema",
+      escape_identifier(cfg.get::<String>("graphboard_schema")?.as_str()),
+    )?;
     cfg.try_into()
   }
 
@@ -0,0 +1,619 @@
+use crate::{config::CONFIG, models::AddJobData};
+use chrono::{DateTime, Datelike, Duration, SecondsFormat, TimeZone, Timelike, Utc};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::{collections::HashMap, fs, lazy::SyncLazy, str::FromStr};
+use tracing::{error, warn};
+
+/// Upper bound on how far in the future we look for the next matching minute,
+/// so that impossible schedules (e.g. `0 0 31 2 *`) terminate.
+const MAX_LOOKAHEAD_DAYS: i64 = 366 * 5;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CrontabSchedule {
+  pub expression: String,
+  pub minutes: Vec<u32>,
+  pub hours: Vec<u32>,
+  pub days: Vec<u32>,
+  pub months: Vec<u32>,
+  pub weekdays: Vec<u32>,
+}
+
+/// Parses an unsigned decimal number, without the sign `u32::from_str` allows.
+fn parse_digits(raw: &str) -> Option<u32> {
+  if raw.is_empty() || !raw.bytes().all(|byte| byte.is_ascii_digit()) {
+    return None;
+  }
+  raw.parse().ok()
+}
+
+/// Parses one time field. As in Graphile Worker, a field is a comma separated
+/// list of numbers, ranges (`1-5`) and wildcards with an optional step
+/// (`*/10`).
+fn parse_field(field: &str, name: &str, min: u32, max: u32) -> Result<Vec<u32>, String> {
+  let parse_number = |raw: &str| -> Result<u32, String> {
+    let value = parse_digits(raw).ok_or_else(|| format!("invalid {} value '{}'", name, raw))?;
+    if value < min || value > max {
+      return Err(format!(
+        "{} value {} is out of range {}-{}",
+        name, value, min, max
+      ));
+    }
+    Ok(value)
+  };
+
+  let mut values = Vec::new();
+  for part in field.split(',') {
+    if let Some(wildcard) = part.strip_prefix('*') {
+      let step = match wildcard.strip_prefix('/') {
+        None if wildcard.is_empty() => 1,
+        Some(step) => parse_digits(step)
+          .filter(|step| *step > 0)
+          .ok_or_else(|| format!("invalid {} step '{}'", name, step))?,
+        None => return Err(format!("invalid {} value '{}'", name, part)),
+      };
+      values.extend((min..=max).step_by(step as usize));
+    } else if let Some((start, end)) = part.split_once('-') {
+      let (start, end) = (parse_number(start)?, parse_number(end)?);
+      if start > end {
+        return Err(format!("invalid {} range '{}'", name, part));
+      }
+      values.extend(start..=end);
+    } else {
+      values.push(parse_number(part)?);
+    }
+  }
+  values.sort_unstable();
+  values.dedup();
+  Ok(values)
+}
+
+impl FromStr for CrontabSchedule {
+  type Err = String;
+
+  fn from_str(expression: &str) -> Result<Self, Self::Err> {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    if fields.len() != 5 {
+      return Err(format!("expected 5 time fields, found {}", fields.len()));
+    }
+    Ok(CrontabSchedule {
+      expression: fields.join(" "),
+      minutes: parse_field(fields[0], "minute", 0, 59)?,
+      hours: parse_field(fields[1], "hour", 0, 23)?,
+      days: parse_field(fields[2], "day", 1, 31)?,
+      months: parse_field(fields[3], "month", 1, 12)?,
+      weekdays: parse_field(fields[4], "weekday", 0, 6)?,
+    })
+  }
+}
+
+impl CrontabSchedule {
+  /// As in vixie cron, Graphile Worker matches a date on either its day of
+  /// month or its weekday when both fields are restricted, and requires both
+  /// otherwise. Schedules are evaluated in UTC.
+  fn matches_date(&self, timestamp: &DateTime<Utc>) -> bool {
+    let day_matches = self.days.contains(&timestamp.day());
+    let weekday_matches = self
+      .weekdays
+      .contains(&timestamp.weekday().num_days_from_sunday());
+    let date_matches = if self.days.len() < 31 && self.weekdays.len() < 7 {
+      day_matches || weekday_matches
+    } else {
+      day_matches && weekday_matches
+    };
+    self.months.contains(&timestamp.month()) && date_matches
+  }
+
+  /// Returns the first matching minute strictly after `after`.
+  pub fn next_after(&self, after: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let mut candidate =
+      Utc
+        .ymd(after.year(), after.month(), after.day())
+        .and_hms(after.hour(), after.minute(), 0)
+        + Duration::minutes(1);
+    let limit = *after + Duration::days(MAX_LOOKAHEAD_DAYS);
+
+    while candidate <= limit {
+      if !self.matches_date(&candidate) {
+        candidate = Utc
+          .ymd(candidate.year(), candidate.month(), candidate.day())
+          .and_hms(0, 0, 0)
+          + Duration::days(1);
+      } else if !self.hours.contains(&candidate.hour()) {
+        candidate = Utc
+          .ymd(candidate.year(), candidate.month(), candidate.day())
+          .and_hms(candidate.hour(), 0, 0)
+          + Duration::hours(1);
+      } else if !self.minutes.contains(&candidate.minute()) {
+        candidate = candidate + Duration::minutes(1);
+      } else {
+        return Some(candidate);
+      }
+    }
+    None
+  }
+
+  pub fn upcoming(&self, after: &DateTime<Utc>, count: usize) -> Vec<DateTime<Utc>> {
+    let mut runs = Vec::with_capacity(count);
+    let mut cursor = *after;
+    while runs.len() < count {
+      match self.next_after(&cursor) {
+        Some(next) => {
+          runs.push(next);
+          cursor = next;
+        }
+        None => break,
+      }
+    }
+    runs
+  }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Crontab {
+  pub identifier: String,
+  pub task_identifier: String,
+  pub schedule: CrontabSchedule,
+  /// Seconds of missed executions the worker backfills when it starts (`fill`)
+  pub backfill_period: Option<u64>,
+  pub queue_name: Option<String>,
+  pub priority: Option<i32>,
+  pub max_attempts: Option<i32>,
+  pub payload: Option<Value>,
+}
+
+/// Splits the first whitespace separated token off `source`.
+fn next_token(source: &str) -> Option<(&str, &str)> {
+  let source = source.trim_start();
+  if source.is_empty() {
+    return None;
+  }
+  let end = source.find(char::is_whitespace).unwrap_or(source.len());
+  Some((&source[..end], &source[end..]))
+}
+
+/// Task identifiers and crontab ids must look like `[_a-zA-Z][_a-zA-Z0-9:-]*`.
+fn is_identifier(value: &str) -> bool {
+  let mut chars = value.chars();
+  matches!(chars.next(), Some(first) if first == '_' || first.is_ascii_alphabetic())
+    && chars.all(|char| char.is_ascii_alphanumeric() || matches!(char, '_' | ':' | '-'))
+}
+
+/// Parses a time phrase such as `1w2d6h` into seconds. Units are `s`, `m`,
+/// `h`, `d` and `w`.
+fn parse_time_phrase(phrase: &str) -> Option<u64> {
+  let mut seconds: u64 = 0;
+  let mut rest = phrase;
+  while !rest.is_empty() {
+    let unit_index = rest.find(|char: char| !char.is_ascii_digit())?;
+    let value = u64::from(parse_digits(&rest[..unit_index])?);
+    let unit = match rest.as_bytes()[unit_index] {
+      b's' => 1,
+      b'm' => 60,
+      b'h' => 60 * 60,
+      b'd' => 24 * 60 * 60,
+      b'w' => 7 * 24 * 60 * 60,
+      _ => return None,
+    };
+    seconds = seconds.checked_add(value.checked_mul(unit)?)?;
+    rest = &rest[unit_index + 1..];
+  }
+  Some(seconds).filter(|_| !phrase.is_empty())
+}
+
+impl Crontab {
+  fn apply_options(&mut self, options: &str) -> Result<(), String> {
+    let mut seen = Vec::new();
+    for option in options.split('&') {
+      let (key, value) = option
+        .split_once('=')
+        .ok_or_else(|| format!("invalid option '{}', expected key=value", option))?;
+      if seen.contains(&key) {
+        return Err(format!("duplicate option '{}'", key));
+      }
+      seen.push(key);
+
+      match key {
+        "id" if is_identifier(value) => self.identifier = String::from(value),
+        "id" => return Err(format!("invalid id '{}'", value)),
+        "fill" => {
+          self.backfill_period = Some(
+            parse_time_phrase(value)
+              .ok_or_else(|| format!("invalid fill '{}', expected e.g. 1w2d3h", value))?,
+          )
+        }
+        "max" => {
+          self.max_attempts = Some(
+            parse_digits(value)
+              .filter(|max_attempts| (1..=i32::MAX as u32).contains(max_attempts))
+              .ok_or_else(|| format!("invalid max '{}'", value))? as i32,
+          )
+        }
+        "queue" if !value.is_empty() => self.queue_name = Some(String::from(value)),
+        "queue" => return Err(String::from("queue must not be empty")),
+        "priority" => {
+          self.priority = Some(
+            value
+              .parse()
+              .map_err(|_| format!("invalid priority '{}'", value))?,
+          )
+        }
+        _ => return Err(format!("unknown option '{}'", key)),
+      }
+    }
+    Ok(())
+  }
+}
+
+impl FromStr for Crontab {
+  type Err = String;
+
+  /// Parses a line of a Graphile Worker crontab file:
+  /// `<minute> <hour> <day> <month> <weekday> <task> [?options] [{payload}]`
+  fn from_str(line: &str) -> Result<Self, Self::Err> {
+    let mut rest = line;
+    let mut time_fields = Vec::with_capacity(5);
+    while time_fields.len() < 5 {
+      match next_token(rest) {
+        Some((field, remaining)) => {
+          time_fields.push(field);
+          rest = remaining;
+        }
+        None => break,
+      }
+    }
+    let schedule: CrontabSchedule = time_fields.join(" ").parse()?;
+    let (task_identifier, mut rest) =
+      next_token(rest).ok_or_else(|| String::from("missing task identifier"))?;
+    if !is_identifier(task_identifier) {
+      return Err(format!("invalid task identifier '{}'", task_identifier));
+    }
+
+    let mut crontab = Crontab {
+      identifier: String::from(task_identifier),
+      task_identifier: String::from(task_identifier),
+      schedule,
+      backfill_period: None,
+      queue_name: None,
+      priority: None,
+      max_attempts: None,
+      payload: None,
+    };
+
+    if let Some((options, remaining)) = next_token(rest)
+      .and_then(|(options, remaining)| Some((options.strip_prefix('?')?, remaining)))
+    {
+      crontab.apply_options(options)?;
+      rest = remaining;
+    }
+
+    let payload = rest.trim();
+    if payload.starts_with('{') {
+      crontab.payload =
+        Some(serde_json::from_str(payload).map_err(|error| format!("invalid payload: {}", error))?);
+    } else if !payload.is_empty() {
+      return Err(format!(
+        "unexpected '{}', expected ?options or a JSON object payload",
+        payload
+      ));
+    }
+
+    Ok(crontab)
+  }
+}
+
+impl Crontab {
+  /// The job Graphile Worker schedules for the `ts` slot, with its `_cron`
+  /// metadata merged into the payload.
+  pub fn job_data(&self, ts: DateTime<Utc>, backfilled: bool) -> AddJobData {
+    let mut payload = match &self.payload {
+      Some(Value::Object(payload)) => payload.clone(),
+      _ => Map::new(),
+    };
+    payload.insert(
+      String::from("_cron"),
+      json!({
+        "ts": ts.to_rfc3339_opts(SecondsFormat::Millis, true),
+        "backfilled": backfilled,
+      }),
+    );
+
+    AddJobData {
+      task_identifier: self.task_identifier.clone(),
+      payload: Some(Value::Object(payload)),
+      queue_name: self.queue_name.clone(),
+      run_at: Some(ts),
+      max_attempts: self.max_attempts,
+      job_key: None,
+      priority: self.priority,
+      flags: None,
+      job_key_mode: None,
+    }
+  }
+}
+
+/// Parses a crontab file, skipping blank lines and comments. Every other line
+/// is returned with its 1-based number, a crontab id used twice being an error.
+pub fn parse_crontab(source: &str) -> Vec<(usize, Result<Crontab, String>)> {
+  let mut identifiers: HashMap<String, usize> = HashMap::new();
+  source
+    .lines()
+    .enumerate()
+    .map(|(index, line)| (index + 1, line.trim()))
+    .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+    .map(|(line_number, line)| {
+      let crontab = line.parse::<Crontab>().and_then(|crontab| {
+        match identifiers.insert(crontab.identifier.clone(), line_number) {
+          Some(previous_line) => Err(format!(
+            "duplicate identifier '{}', already used on line {}",
+            crontab.identifier, previous_line
+          )),
+          None => Ok(crontab),
+        }
+      });
+      (line_number, crontab)
+    })
+    .collect()
+}
+
+fn load_crontab() -> Vec<Crontab> {
+  let path = match &(*CONFIG).crontab.file {
+    Some(path) => path,
+    None => return Vec::new(),
+  };
+
+  let source = match fs::read_to_string(path) {
+    Ok(source) => source,
+    Err(error) => {
+      error!(
+        error = error.to_string().as_str(),
+        path = path.as_str(),
+        "Error while reading crontab file"
+      );
+      return Vec::new();
+    }
+  };
+
+  parse_crontab(&source)
+    .into_iter()
+    .filter_map(|(line, crontab)| match crontab {
+      Ok(crontab) => Some(crontab),
+      Err(error) => {
+        warn!(
+          error = error.as_str(),
+          path = path.as_str(),
+          line,
+          "Ignoring invalid crontab line"
+        );
+        None
+      }
+    })
+    .collect()
+}
+
+pub static CRONTAB: SyncLazy<Vec<Crontab>> = SyncLazy::new(load_crontab);
+
+pub fn find_crontab<I: AsRef<str>>(identifier: I) -> Option<&'static Crontab> {
+  CRONTAB
+    .iter()
+    .find(|crontab| crontab.identifier == identifier.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn schedule(expression: &str) -> CrontabSchedule {
+    expression.parse().unwrap()
+  }
+
+  fn at(timestamp: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(timestamp)
+      .unwrap()
+      .with_timezone(&Utc)
+  }
+
+  fn next_after(expression: &str, after: &str) -> Option<DateTime<Utc>> {
+    schedule(expression).next_after(&at(after))
+  }
+
+  #[test]
+  fn parses_wildcards_and_steps() {
+    let schedule = schedule("* */6 */10 * *");
+    assert_eq!(schedule.minutes, (0..=59).collect::<Vec<u32>>());
+    assert_eq!(schedule.hours, vec![0, 6, 12, 18]);
+    assert_eq!(schedule.days, vec![1, 11, 21, 31]);
+    assert_eq!(schedule.weekdays, (0..=6).collect::<Vec<u32>>());
+  }
+
+  #[test]
+  fn parses_ranges_and_lists() {
+    let schedule = schedule("0,30,0 9-11,14 1 1-3,12 1-5");
+    assert_eq!(schedule.minutes, vec![0, 30]);
+    assert_eq!(schedule.hours, vec![9, 10, 11, 14]);
+    assert_eq!(schedule.days, vec![1]);
+    assert_eq!(schedule.months, vec![1, 2, 3, 12]);
+    assert_eq!(schedule.weekdays, vec![1, 2, 3, 4, 5]);
+  }
+
+  #[test]
+  fn rejects_invalid_time_fields() {
+    for (expression, error) in [
+      ("60 * * * *", "minute value 60 is out of range 0-59"),
+      ("* 24 * * *", "hour value 24 is out of range 0-23"),
+      ("* * 0 * *", "day value 0 is out of range 1-31"),
+      ("* * * 13 *", "month value 13 is out of range 1-12"),
+      ("* * * * 7", "weekday value 7 is out of range 0-6"),
+      ("*/0 * * * *", "invalid minute step '0'"),
+      ("5-1 * * * *", "invalid minute range '5-1'"),
+      ("+1 * * * *", "invalid minute value '+1'"),
+      ("*5 * * * *", "invalid minute value '*5'"),
+      ("* * * *", "expected 5 time fields, found 4"),
+    ] {
+      assert_eq!(
+        expression.parse::<CrontabSchedule>().unwrap_err(),
+        error,
+        "{}",
+        expression
+      );
+    }
+  }
+
+  #[test]
+  fn parses_fill_phrases() {
+    assert_eq!(parse_time_phrase("10s"), Some(10));
+    assert_eq!(parse_time_phrase("30m"), Some(30 * 60));
+    assert_eq!(
+      parse_time_phrase("1w2d6h"),
+      Some(7 * 86400 + 2 * 86400 + 6 * 3600)
+    );
+    for phrase in ["", "h", "1", "1x", "1h2", "-1h", "99999999999999w"] {
+      assert_eq!(parse_time_phrase(phrase), None, "{}", phrase);
+    }
+
+    let crontab: Crontab = "0 4 * * * task ?fill=2d".parse().unwrap();
+    assert_eq!(crontab.backfill_period, Some(2 * 86400));
+    assert_eq!(
+      "0 4 * * * task ?fill=2y".parse::<Crontab>().unwrap_err(),
+      "invalid fill '2y', expected e.g. 1w2d3h"
+    );
+  }
+
+  #[test]
+  fn parses_options() {
+    let crontab: Crontab = "0 4 * * * task ?id=nightly&max=3&queue=reports&priority=-1"
+      .parse()
+      .unwrap();
+    assert_eq!(crontab.identifier, "nightly");
+    assert_eq!(crontab.task_identifier, "task");
+    assert_eq!(crontab.max_attempts, Some(3));
+    assert_eq!(crontab.queue_name.as_deref(), Some("reports"));
+    assert_eq!(crontab.priority, Some(-1));
+    assert_eq!(crontab.payload, None);
+  }
+
+  #[test]
+  fn rejects_duplicate_and_unknown_options() {
+    for (line, error) in [
+      ("* * * * * task ?max=2&max=3", "duplicate option 'max'"),
+      ("* * * * * task ?every=1h", "unknown option 'every'"),
+      (
+        "* * * * * task ?max",
+        "invalid option 'max', expected key=value",
+      ),
+      ("* * * * * task ?max=0", "invalid max '0'"),
+      ("* * * * * task ?id=1st", "invalid id '1st'"),
+      ("* * * * * task ?queue=", "queue must not be empty"),
+    ] {
+      assert_eq!(line.parse::<Crontab>().unwrap_err(), error, "{}", line);
+    }
+  }
+
+  #[test]
+  fn parses_payloads() {
+    let crontab: Crontab = r#"* * * * * task ?max=1 {"kind": "daily"}"#.parse().unwrap();
+    assert_eq!(crontab.payload, Some(json!({ "kind": "daily" })));
+    let crontab: Crontab = r#"* * * * * task {"n": 1}"#.parse().unwrap();
+    assert_eq!(crontab.payload, Some(json!({ "n": 1 })));
+
+    assert!(r#"* * * * * task {"kind":"#
+      .parse::<Crontab>()
+      .unwrap_err()
+      .starts_with("invalid payload: "));
+    for (line, error) in [
+      (
+        "* * * * * task now",
+        "unexpected 'now', expected ?options or a JSON object payload",
+      ),
+      (
+        "* * * * * task ?max=1 [1]",
+        "unexpected '[1]', expected ?options or a JSON object payload",
+      ),
+      ("* * * * *", "missing task identifier"),
+      ("* * * * * 1task", "invalid task identifier '1task'"),
+    ] {
+      assert_eq!(line.parse::<Crontab>().unwrap_err(), error, "{}", line);
+    }
+  }
+
+  #[test]
+  fn rejects_duplicate_identifiers() {
+    let lines = parse_crontab(
+      "# nightly tasks\n0 4 * * * task\n\n0 5 * * * task\n0 6 * * * task ?id=task_at_six\n",
+    );
+    let lines: Vec<(usize, Result<String, String>)> = lines
+      .into_iter()
+      .map(|(line, crontab)| (line, crontab.map(|crontab| crontab.identifier)))
+      .collect();
+    assert_eq!(
+      lines,
+      vec![
+        (2, Ok(String::from("task"))),
+        (
+          4,
+          Err(String::from(
+            "duplicate identifier 'task', already used on line 2"
+          ))
+        ),
+        (5, Ok(String::from("task_at_six"))),
+      ]
+    );
+  }
+
+  #[test]
+  fn next_run_is_strictly_after() {
+    assert_eq!(
+      next_after("*/15 * * * *", "2026-10-18T10:15:00Z"),
+      Some(at("2026-10-18T10:30:00Z"))
+    );
+    assert_eq!(
+      next_after("*/15 * * * *", "2026-10-18T10:14:59Z"),
+      Some(at("2026-10-18T10:15:00Z"))
+    );
+  }
+
+  #[test]
+  fn next_run_crosses_month_and_year_boundaries() {
+    assert_eq!(
+      next_after("0 0 1 * *", "2026-01-31T12:00:00Z"),
+      Some(at("2026-02-01T00:00:00Z"))
+    );
+    assert_eq!(
+      next_after("0 0 31 * *", "2026-04-01T00:00:00Z"),
+      Some(at("2026-05-31T00:00:00Z"))
+    );
+    assert_eq!(
+      next_after("30 23 31 12 *", "2026-12-31T23:30:00Z"),
+      Some(at("2027-12-31T23:30:00Z"))
+    );
+    assert_eq!(
+      next_after("0 0 29 2 *", "2026-03-01T00:00:00Z"),
+      Some(at("2028-02-29T00:00:00Z"))
+    );
+    assert_eq!(next_after("0 0 31 2 *", "2026-01-01T00:00:00Z"), None);
+  }
+
+  #[test]
+  fn next_run_matches_day_or_weekday_when_both_are_restricted() {
+    // 2026-12-05 is a Saturday, the 13th a Sunday
+    assert_eq!(
+      next_after("0 4 13 * 5", "2026-12-05T00:00:00Z"),
+      Some(at("2026-12-11T04:00:00Z"))
+    );
+    assert_eq!(
+      next_after("0 4 13 * 5", "2026-12-11T04:00:00Z"),
+      Some(at("2026-12-13T04:00:00Z"))
+    );
+    // Only the restricted field applies when the other is a wildcard
+    assert_eq!(
+      next_after("0 4 * * 5", "2026-12-11T04:00:00Z"),
+      Some(at("2026-12-18T04:00:00Z"))
+    );
+    assert_eq!(
+      next_after("0 4 13 * *", "2026-12-11T04:00:00Z"),
+      Some(at("2026-12-13T04:00:00Z"))
+    );
+  }
+}
@@ -8,7 +8,7 @@ use serde_json::Value;
 
 #[derive(Serialize, Error, Display, Debug)]
 #[serde(rename_all(serialize = "camelCase"))]
-#[display(fmt = "{}", self)]
+#[display(fmt = "{}", err_code)]
 pub struct HttpError {
   #[serde(skip_serializing)]
   http_code: StatusCode,
@@ -25,6 +25,22 @@ impl HttpError {
     }
   }
 
+  pub fn unauthorized(err_code: &str, data: Option<Value>) -> HttpError {
+    HttpError {
+      http_code: StatusCode::UNAUTHORIZED,
+      err_code: String::from(err_code),
+      data,
+    }
+  }
+
+  pub fn forbidden(err_code: &str, data: Option<Value>) -> HttpError {
+    HttpError {
+      http_code: StatusCode::FORBIDDEN,
+      err_code: String::from(err_code),
+      data,
+    }
+  }
+
   pub fn not_found(err_code: &str, data: Option<Value>) -> HttpError {
     HttpError {
       http_code: StatusCode::NOT_FOUND,
@@ -40,6 +56,22 @@ impl HttpError {
       data,
     }
   }
+
+  pub fn conflict(err_code: &str, data: Option<Value>) -> HttpError {
+    HttpError {
+      http_code: StatusCode::CONFLICT,
+      err_code: String::from(err_code),
+      data,
+    }
+  }
+
+  pub fn service_unavailable(err_code: &str, data: Option<Value>) -> HttpError {
+    HttpError {
+      http_code: StatusCode::SERVICE_UNAVAILABLE,
+      err_code: String::from(err_code),
+      data,
+    }
+  }
 }
 
 impl From<&str> for HttpError {
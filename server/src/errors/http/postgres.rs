@@ -1,6 +1,7 @@
-use crate::errors::HttpError;
+use crate::{errors::HttpError, repositories::RepositoryError};
 use deadpool_postgres::PoolError;
 use serde_json::json;
+use tokio_postgres::error::Error as PGError;
 
 impl From<PoolError> for HttpError {
   fn from(pool_error: PoolError) -> Self {
@@ -25,3 +26,10 @@ impl From<PoolError> for HttpError {
     }
   }
 }
+
+/// Errors from transactions opened by the services map as repository ones.
+impl From<PGError> for HttpError {
+  fn from(error: PGError) -> Self {
+    RepositoryError::PGError(error).into()
+  }
+}
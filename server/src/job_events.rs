@@ -0,0 +1,117 @@
+use crate::config::CONFIG;
+use actix_web::rt;
+use futures::{stream, StreamExt};
+use serde::Serialize;
+use std::time::Duration;
+use tokio::{sync::broadcast, time::sleep};
+use tokio_postgres::{error::Error as PGError, AsyncMessage, Config as PGConfig, NoTls};
+use tracing::{error, info, warn};
+
+/// Channel notified by Graphile Worker's `tg_jobs__notify_new_jobs` trigger.
+pub const JOBS_INSERT_CHANNEL: &str = "jobs:insert";
+const CHANNEL_CAPACITY: usize = 256;
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JobEvent {
+  pub channel: String,
+  pub payload: String,
+}
+
+/// Fans out Postgres notifications to every subscriber. A single dedicated
+/// connection, kept outside of the deadpool `Pool`, holds the `LISTEN`.
+#[derive(Clone)]
+pub struct JobEvents {
+  sender: broadcast::Sender<JobEvent>,
+}
+
+impl JobEvents {
+  pub fn start() -> JobEvents {
+    let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+    let pg_config = (*CONFIG)
+      .pg
+      .get_pg_config()
+      .expect("Error while creating job events connection config");
+
+    rt::spawn(listen(pg_config, sender.clone()));
+
+    JobEvents { sender }
+  }
+
+  pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+    self.sender.subscribe()
+  }
+}
+
+async fn listen(pg_config: PGConfig, sender: broadcast::Sender<JobEvent>) {
+  let mut delay = MIN_RECONNECT_DELAY;
+  let mut connected_once = false;
+
+  loop {
+    let result = listen_once(&pg_config, &sender, || {
+      if connected_once {
+        // Notifications may have been missed while disconnected
+        let _ = sender.send(JobEvent {
+          channel: String::from("reconnect"),
+          payload: String::new(),
+        });
+      }
+      connected_once = true;
+      delay = MIN_RECONNECT_DELAY;
+    })
+    .await;
+
+    match result {
+      Ok(()) => warn!("Job events connection closed, reconnecting"),
+      Err(error) => error!(
+        error = error.to_string().as_str(),
+        "Job events connection failed, reconnecting"
+      ),
+    }
+
+    sleep(delay).await;
+    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+  }
+}
+
+async fn listen_once<F: FnOnce()>(
+  pg_config: &PGConfig,
+  sender: &broadcast::Sender<JobEvent>,
+  on_listening: F,
+) -> Result<(), PGError> {
+  let (client, mut connection) = pg_config.connect(NoTls).await?;
+
+  let sender = sender.clone();
+  let forward = rt::spawn(async move {
+    let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+    while let Some(message) = messages.next().await {
+      if let AsyncMessage::Notification(notification) = message? {
+        // Sending only fails when nobody is subscribed, which is fine
+        let _ = sender.send(JobEvent {
+          channel: String::from(notification.channel()),
+          payload: String::from(notification.payload()),
+        });
+      }
+    }
+    Ok::<(), PGError>(())
+  });
+
+  if let Err(error) = client
+    .batch_execute(&format!("listen \"{}\"", JOBS_INSERT_CHANNEL))
+    .await
+  {
+    forward.abort();
+    return Err(error);
+  }
+  info!(channel = JOBS_INSERT_CHANNEL, "Listening for job events");
+  on_listening();
+
+  let result = match forward.await {
+    Ok(result) => result,
+    Err(_) => Ok(()),
+  };
+  drop(client);
+  result
+}
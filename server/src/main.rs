@@ -2,13 +2,28 @@
 #![feature(async_closure)]
 
 mod config;
+mod crontab;
 pub mod errors;
+mod job_events;
+mod metrics;
+mod middlewares;
+mod migrations;
 mod models;
+mod pauser;
 mod repositories;
+mod sampler;
 mod services;
 mod telemetry;
 
-use crate::{config::CONFIG, services::api_services, telemetry::init_telemetry};
+use crate::{
+  config::CONFIG,
+  job_events::JobEvents,
+  metrics::MetricsRootSpanBuilder,
+  pauser::start_pauser,
+  sampler::start_sampler,
+  services::{api_services, metrics_route},
+  telemetry::init_telemetry,
+};
 use actix_web::{web::Data, App, HttpServer};
 use deadpool_postgres::Runtime::Tokio1;
 use std::io;
@@ -23,12 +38,20 @@ async fn main() -> io::Result<()> {
     .pg
     .create_pool(Some(Tokio1), NoTls)
     .expect("Error while creating DB pool");
+  migrations::migrate(&pool)
+    .await
+    .expect("Error while migrating the graphboard schema");
+  let job_events = JobEvents::start();
+  start_sampler(pool.clone());
+  start_pauser(pool.clone());
 
   let app = move || {
     App::new()
-      .wrap(TracingLogger::default())
+      .wrap(TracingLogger::<MetricsRootSpanBuilder>::new())
       .app_data(Data::new(pool.clone()))
+      .app_data(Data::new(job_events.clone()))
       .service(api_services())
+      .service(metrics_route)
   };
 
   let server_addr = (*CONFIG).server_addr();
@@ -0,0 +1,130 @@
+use actix_web::{
+  dev::{ServiceRequest, ServiceResponse},
+  Error, HttpMessage,
+};
+use std::{collections::BTreeMap, fmt::Display, lazy::SyncLazy, sync::Mutex, time::Instant};
+use tracing::Span;
+use tracing_actix_web::{DefaultRootSpanBuilder, RootSpanBuilder};
+
+/// Metrics in the Prometheus text exposition format.
+#[derive(Default)]
+pub struct MetricsText(String);
+
+fn escape_label_value(value: &str) -> String {
+  value
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+impl MetricsText {
+  pub fn family(&mut self, name: &str, kind: &str, help: &str) {
+    self.0 += &format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind);
+  }
+
+  pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+    self.0 += name;
+    if !labels.is_empty() {
+      let labels: Vec<String> = labels
+        .iter()
+        .map(|(label, value)| format!("{}=\"{}\"", label, escape_label_value(value)))
+        .collect();
+      self.0 += &format!("{{{}}}", labels.join(","));
+    }
+    self.0 += &format!(" {}\n", value);
+  }
+
+  pub fn into_string(self) -> String {
+    self.0
+  }
+}
+
+const HTTP_DURATION_BUCKETS: [f64; 11] = [
+  0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Default)]
+struct Histogram {
+  buckets: [u64; HTTP_DURATION_BUCKETS.len()],
+  sum: f64,
+  count: u64,
+}
+
+impl Histogram {
+  fn observe(&mut self, value: f64) {
+    for (bucket, bound) in self.buckets.iter_mut().zip(HTTP_DURATION_BUCKETS) {
+      if value <= bound {
+        *bucket += 1;
+      }
+    }
+    self.sum += value;
+    self.count += 1;
+  }
+}
+
+/// Request durations keyed by method, route pattern and status code.
+static HTTP_REQUESTS: SyncLazy<Mutex<BTreeMap<(String, String, u16), Histogram>>> =
+  SyncLazy::new(|| Mutex::new(BTreeMap::new()));
+
+pub fn write_http_metrics(text: &mut MetricsText) {
+  let name = "graphboard_http_request_duration_seconds";
+  text.family(name, "histogram", "Duration of the HTTP requests");
+  let requests = HTTP_REQUESTS.lock().unwrap();
+  for ((method, route, status), histogram) in requests.iter() {
+    let status = status.to_string();
+    let labels = [
+      ("method", method.as_str()),
+      ("route", route.as_str()),
+      ("status", status.as_str()),
+    ];
+    for (count, bound) in histogram.buckets.iter().zip(HTTP_DURATION_BUCKETS) {
+      let bound = bound.to_string();
+      let mut bucket_labels = labels.to_vec();
+      bucket_labels.push(("le", bound.as_str()));
+      text.sample(&format!("{}_bucket", name), &bucket_labels, count);
+    }
+    let mut bucket_labels = labels.to_vec();
+    bucket_labels.push(("le", "+Inf"));
+    text.sample(&format!("{}_bucket", name), &bucket_labels, histogram.count);
+    text.sample(&format!("{}_sum", name), &labels, histogram.sum);
+    text.sample(&format!("{}_count", name), &labels, histogram.count);
+  }
+}
+
+struct RequestStart(Instant);
+
+/// Default root span of the `TracingLogger`, also timing every request for the
+/// HTTP metrics.
+pub struct MetricsRootSpanBuilder;
+
+impl RootSpanBuilder for MetricsRootSpanBuilder {
+  fn on_request_start(request: &ServiceRequest) -> Span {
+    request
+      .extensions_mut()
+      .insert(RequestStart(Instant::now()));
+    DefaultRootSpanBuilder::on_request_start(request)
+  }
+
+  fn on_request_end<B>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+    if let Ok(response) = outcome {
+      let request = response.request();
+      if let Some(RequestStart(start)) = request.extensions().get::<RequestStart>() {
+        // Unmatched paths are grouped to keep the number of series bounded
+        let route = request
+          .match_pattern()
+          .unwrap_or_else(|| String::from("unmatched"));
+        HTTP_REQUESTS
+          .lock()
+          .unwrap()
+          .entry((
+            request.method().to_string(),
+            route,
+            response.status().as_u16(),
+          ))
+          .or_default()
+          .observe(start.elapsed().as_secs_f64());
+      }
+    }
+    DefaultRootSpanBuilder::on_request_end(span, outcome);
+  }
+}
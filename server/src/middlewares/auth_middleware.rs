@@ -0,0 +1,144 @@
+use crate::{
+  errors::HttpError,
+  models::{JobScope, Role},
+  repositories::authenticate_api_token,
+  CONFIG,
+};
+use actix_web::{
+  dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+  http::header::AUTHORIZATION,
+  web::Data,
+  Error, FromRequest, HttpMessage, HttpRequest,
+};
+use deadpool_postgres::Pool;
+use futures::future::{ready, LocalBoxFuture, Ready};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::rc::Rc;
+
+/// Who is calling the API, available to routes through the request extensions.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Principal {
+  /// Stored token used, `None` for tokens from the configuration or when auth
+  /// is disabled
+  pub token_id: Option<i32>,
+  pub name: String,
+  pub role: Role,
+  pub scope: JobScope,
+}
+
+impl Principal {
+  /// Unrestricted principal used for configuration tokens and when auth is
+  /// disabled
+  fn admin(name: &str) -> Self {
+    Principal {
+      token_id: None,
+      name: String::from(name),
+      role: Role::Admin,
+      scope: JobScope::default(),
+    }
+  }
+}
+
+impl FromRequest for Principal {
+  type Error = HttpError;
+  type Future = Ready<Result<Self, Self::Error>>;
+
+  fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+    ready(
+      req
+        .extensions()
+        .get::<Principal>()
+        .cloned()
+        .ok_or_else(|| HttpError::unauthorized("UNAUTH", None)),
+    )
+  }
+}
+
+pub fn hash_token(token: &str) -> String {
+  format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+async fn authenticate(req: &ServiceRequest) -> Result<Principal, HttpError> {
+  if !(*CONFIG).auth.enabled {
+    return Ok(Principal::admin("anonymous"));
+  }
+
+  let token = req
+    .headers()
+    .get(AUTHORIZATION)
+    .and_then(|authorization| authorization.to_str().ok())
+    .and_then(|authorization| authorization.strip_prefix("Bearer "))
+    .map(str::trim)
+    .filter(|token| !token.is_empty())
+    .ok_or_else(|| HttpError::unauthorized("UNAUTH", None))?;
+  let token_hash = hash_token(token);
+
+  if (*CONFIG)
+    .auth
+    .token_hashes()
+    .any(|config_hash| config_hash.eq_ignore_ascii_case(&token_hash))
+  {
+    return Ok(Principal::admin("config"));
+  }
+
+  let pool = req
+    .app_data::<Data<Pool>>()
+    .ok_or_else(|| HttpError::internal_server_error("NOPOOL", None))?;
+  authenticate_api_token(&pool.get().await?, &token_hash)
+    .await?
+    .map(|api_token| Principal {
+      token_id: Some(api_token.id),
+      name: api_token.name,
+      role: api_token.role,
+      scope: api_token.scope,
+    })
+    .ok_or_else(|| HttpError::unauthorized("IVTKN", None))
+}
+
+/// Rejects requests without a valid `Authorization: Bearer <token>` header.
+pub struct Authentication;
+
+impl<S, B> Transform<S, ServiceRequest> for Authentication
+where
+  S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+  B: 'static,
+{
+  type Response = ServiceResponse<B>;
+  type Error = Error;
+  type Transform = AuthenticationMiddleware<S>;
+  type InitError = ();
+  type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+  fn new_transform(&self, service: S) -> Self::Future {
+    ready(Ok(AuthenticationMiddleware {
+      service: Rc::new(service),
+    }))
+  }
+}
+
+pub struct AuthenticationMiddleware<S> {
+  service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthenticationMiddleware<S>
+where
+  S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+  B: 'static,
+{
+  type Response = ServiceResponse<B>;
+  type Error = Error;
+  type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+  forward_ready!(service);
+
+  fn call(&self, req: ServiceRequest) -> Self::Future {
+    let service = self.service.clone();
+    Box::pin(async move {
+      let principal = authenticate(&req).await?;
+      req.extensions_mut().insert(principal);
+      service.call(req).await
+    })
+  }
+}
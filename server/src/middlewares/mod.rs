@@ -0,0 +1,5 @@
+mod auth_middleware;
+mod role_middleware;
+
+pub use auth_middleware::*;
+pub use role_middleware::*;
@@ -0,0 +1,80 @@
+use crate::{errors::HttpError, middlewares::Principal, models::Role};
+use actix_web::{
+  dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+  Error, HttpMessage,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use serde_json::json;
+use std::rc::Rc;
+
+fn authorize(req: &ServiceRequest, required: Role) -> Result<(), HttpError> {
+  let role = req
+    .extensions()
+    .get::<Principal>()
+    .map(|principal| principal.role)
+    .ok_or_else(|| HttpError::unauthorized("UNAUTH", None))?;
+  if role < required {
+    return Err(HttpError::forbidden(
+      "FRBDN",
+      Some(json!({ "requiredRole": required, "role": role })),
+    ));
+  }
+  Ok(())
+}
+
+pub struct RoleMiddleware<S> {
+  service: Rc<S>,
+  required: Role,
+}
+
+impl<S, B> Service<ServiceRequest> for RoleMiddleware<S>
+where
+  S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+  B: 'static,
+{
+  type Response = ServiceResponse<B>;
+  type Error = Error;
+  type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+  forward_ready!(service);
+
+  fn call(&self, req: ServiceRequest) -> Self::Future {
+    let service = self.service.clone();
+    let required = self.required;
+    Box::pin(async move {
+      authorize(&req, required)?;
+      service.call(req).await
+    })
+  }
+}
+
+/// Role requirements are unit structs so they can be given to the route
+/// macros, as in `#[post("/remove", wrap = "RequireAdmin")]`.
+macro_rules! role_requirement {
+  ($name:ident, $role:expr) => {
+    pub struct $name;
+
+    impl<S, B> Transform<S, ServiceRequest> for $name
+    where
+      S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+      B: 'static,
+    {
+      type Response = ServiceResponse<B>;
+      type Error = Error;
+      type Transform = RoleMiddleware<S>;
+      type InitError = ();
+      type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+      fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RoleMiddleware {
+          service: Rc::new(service),
+          required: $role,
+        }))
+      }
+    }
+  };
+}
+
+role_requirement!(RequireViewer, Role::Viewer);
+role_requirement!(RequireOperator, Role::Operator);
+role_requirement!(RequireAdmin, Role::Admin);
@@ -0,0 +1,8 @@
+create table :GRAPHBOARD_SCHEMA.api_tokens (
+  id serial primary key,
+  name text not null,
+  token_hash text not null unique,
+  created_at timestamptz not null default now(),
+  last_used_at timestamptz,
+  revoked_at timestamptz
+);
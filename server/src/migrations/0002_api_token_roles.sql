@@ -0,0 +1,4 @@
+alter table :GRAPHBOARD_SCHEMA.api_tokens
+  add column role text not null default 'viewer' check (role in ('viewer', 'operator', 'admin')),
+  add column queue_patterns text[],
+  add column task_patterns text[];
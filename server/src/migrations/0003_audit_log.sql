@@ -0,0 +1,24 @@
+create table :GRAPHBOARD_SCHEMA.audit_log (
+  id bigserial primary key,
+  created_at timestamptz not null default now(),
+  actor_token_id integer,
+  actor text not null,
+  route text not null,
+  body jsonb,
+  job_ids bigint[] not null default '{}',
+  before jsonb,
+  after jsonb
+);
+
+create index audit_log_created_at_idx on :GRAPHBOARD_SCHEMA.audit_log (created_at);
+create index audit_log_job_ids_idx on :GRAPHBOARD_SCHEMA.audit_log using gin (job_ids);
+
+create function :GRAPHBOARD_SCHEMA.audit_log_append_only() returns trigger as $$
+begin
+  raise exception 'audit_log is append-only';
+end;
+$$ language plpgsql;
+
+create trigger _100_append_only
+  before update or delete on :GRAPHBOARD_SCHEMA.audit_log
+  for each row execute procedure :GRAPHBOARD_SCHEMA.audit_log_append_only();
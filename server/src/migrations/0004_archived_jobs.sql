@@ -0,0 +1,36 @@
+create table :GRAPHBOARD_SCHEMA.archived_jobs (
+  id bigint not null,
+  queue_name text,
+  task_identifier text not null,
+  payload json not null,
+  priority integer not null,
+  run_at timestamptz not null,
+  attempts integer not null,
+  max_attempts integer not null,
+  last_error text,
+  created_at timestamptz not null,
+  updated_at timestamptz not null,
+  key text,
+  locked_at timestamptz,
+  locked_by text,
+  revision integer not null,
+  flags jsonb,
+  archived_at timestamptz not null default now()
+);
+
+create index archived_jobs_id_idx on :GRAPHBOARD_SCHEMA.archived_jobs (id);
+create index archived_jobs_archived_at_idx on :GRAPHBOARD_SCHEMA.archived_jobs (archived_at);
+
+create function :GRAPHBOARD_SCHEMA.archive_job() returns trigger as $$
+begin
+  insert into :GRAPHBOARD_SCHEMA.archived_jobs (
+    id, queue_name, task_identifier, payload, priority, run_at, attempts, max_attempts,
+    last_error, created_at, updated_at, key, locked_at, locked_by, revision, flags
+  ) values (
+    old.id, old.queue_name, old.task_identifier, old.payload, old.priority, old.run_at,
+    old.attempts, old.max_attempts, old.last_error, old.created_at, old.updated_at, old.key,
+    old.locked_at, old.locked_by, old.revision, old.flags
+  );
+  return null;
+end;
+$$ language plpgsql;
@@ -0,0 +1,8 @@
+create table :GRAPHBOARD_SCHEMA.job_samples (
+  sampled_at timestamptz not null,
+  task_identifier text not null,
+  state text not null,
+  count double precision not null,
+  downsampled boolean not null default false,
+  primary key (sampled_at, task_identifier, state, downsampled)
+);
@@ -0,0 +1,25 @@
+create table :GRAPHBOARD_SCHEMA.paused_queues (
+  queue_name text primary key,
+  paused_by text not null,
+  paused_at timestamptz not null default now()
+);
+
+-- Job queue rows are deleted once empty, this holds a paused queue back again
+-- as soon as a new job recreates its row. The marker must stay in sync with
+-- `PAUSED_LOCK_PREFIX`.
+create function :GRAPHBOARD_SCHEMA.pause_job_queue() returns trigger as $$
+declare
+  v_paused_by text;
+begin
+  select p.paused_by into v_paused_by
+    from :GRAPHBOARD_SCHEMA.paused_queues p
+    where p.queue_name = new.queue_name;
+
+  if found then
+    new.locked_by := 'graphboard:paused:' || v_paused_by;
+    new.locked_at := now();
+  end if;
+
+  return new;
+end;
+$$ language plpgsql;
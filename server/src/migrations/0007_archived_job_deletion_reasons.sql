@@ -0,0 +1,35 @@
+-- Archived jobs keep their columns as they were when deleted, which do not
+-- tell how the job ended: a job completed by a worker is still locked.
+alter table :GRAPHBOARD_SCHEMA.archived_jobs
+  add column deletion_reason text check (deletion_reason in ('completed', 'failed', 'removed'));
+
+update :GRAPHBOARD_SCHEMA.archived_jobs set deletion_reason = case
+  when locked_at is not null then 'completed'
+  when attempts >= max_attempts then 'failed'
+  else 'removed'
+end;
+
+alter table :GRAPHBOARD_SCHEMA.archived_jobs alter column deletion_reason set not null;
+
+-- Graphboard sets `graphboard.deletion_reason` for the transaction when it
+-- completes or removes jobs. Other deletions are told apart by their columns:
+-- workers only complete jobs they hold locked.
+create or replace function :GRAPHBOARD_SCHEMA.archive_job() returns trigger as $$
+begin
+  insert into :GRAPHBOARD_SCHEMA.archived_jobs (
+    id, queue_name, task_identifier, payload, priority, run_at, attempts, max_attempts,
+    last_error, created_at, updated_at, key, locked_at, locked_by, revision, flags,
+    deletion_reason
+  ) values (
+    old.id, old.queue_name, old.task_identifier, old.payload, old.priority, old.run_at,
+    old.attempts, old.max_attempts, old.last_error, old.created_at, old.updated_at, old.key,
+    old.locked_at, old.locked_by, old.revision, old.flags,
+    coalesce(nullif(current_setting('graphboard.deletion_reason', true), ''), case
+      when old.locked_at is not null then 'completed'
+      when old.attempts >= old.max_attempts then 'failed'
+      else 'removed'
+    end)
+  );
+  return null;
+end;
+$$ language plpgsql;
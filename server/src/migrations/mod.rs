@@ -0,0 +1,130 @@
+use crate::{repositories::RepositoryError, CONFIG};
+use deadpool_postgres::{Pool, Transaction};
+use tracing::info;
+
+/// Migrations of the graphboard schema, applied in order at startup.
+/// `:GRAPHBOARD_SCHEMA` is replaced by the configured schema name.
+const MIGRATIONS: &[(i32, &str, &str)] = &[
+  (1, "api_tokens", include_str!("0001_api_tokens.sql")),
+  (
+    2,
+    "api_token_roles",
+    include_str!("0002_api_token_roles.sql"),
+  ),
+  (3, "audit_log", include_str!("0003_audit_log.sql")),
+  (4, "archived_jobs", include_str!("0004_archived_jobs.sql")),
+  (5, "job_samples", include_str!("0005_job_samples.sql")),
+  (6, "paused_queues", include_str!("0006_paused_queues.sql")),
+  (
+    7,
+    "archived_job_deletion_reasons",
+    include_str!("0007_archived_job_deletion_reasons.sql"),
+  ),
+];
+
+/// Changing triggers locks the graphile worker tables, so they are only
+/// created or dropped when missing or unwanted.
+async fn trigger_exists(
+  transaction: &Transaction<'_>,
+  table: &str,
+  name: &str,
+) -> Result<bool, RepositoryError> {
+  Ok(
+    transaction
+      .query_one(
+        "select exists(select 1 from pg_trigger t where t.tgrelid = $1::text::regclass and \
+         t.tgname = $2::text)",
+        &[
+          &format!("{}.{}", (*CONFIG).graphile_worker_schema, table),
+          &name,
+        ],
+      )
+      .await?
+      .try_get(0)?,
+  )
+}
+
+pub async fn migrate(pool: &Pool) -> Result<(), RepositoryError> {
+  let schema = &(*CONFIG).graphboard_schema;
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+
+  // Serializes concurrent startups of several graphboard instances
+  transaction
+    .execute(
+      "select pg_advisory_xact_lock(hashtext('graphboard:migrate'))",
+      &[],
+    )
+    .await?;
+  transaction
+    .batch_execute(&format!(
+      r#"create schema if not exists {schema};
+        create table if not exists {schema}.migrations (
+          id integer primary key,
+          name text not null,
+          applied_at timestamptz not null default now()
+        );"#,
+      schema = schema
+    ))
+    .await?;
+
+  let last_applied: i32 = transaction
+    .query_one(
+      &format!("select coalesce(max(id), 0) from {}.migrations", schema),
+      &[],
+    )
+    .await?
+    .try_get(0)?;
+
+  for (id, name, sql) in MIGRATIONS.iter().filter(|(id, _, _)| *id > last_applied) {
+    info!(id, name, "Applying migration");
+    transaction
+      .batch_execute(&sql.replace(":GRAPHBOARD_SCHEMA", schema))
+      .await?;
+    transaction
+      .execute(
+        &format!(
+          "insert into {}.migrations (id, name) values ($1, $2)",
+          schema
+        ),
+        &[id, name],
+      )
+      .await?;
+  }
+
+  // Jobs deleted from the graphile worker schema are archived when enabled
+  let archiving = trigger_exists(&transaction, "jobs", "_900_graphboard_archive").await?;
+  if (*CONFIG).archive.enabled && !archiving {
+    info!("Enabling job archiving");
+    transaction
+      .batch_execute(&format!(
+        "create trigger _900_graphboard_archive after delete on {}.jobs for each row execute \
+         procedure {}.archive_job()",
+        (*CONFIG).graphile_worker_schema,
+        schema
+      ))
+      .await?;
+  } else if !(*CONFIG).archive.enabled && archiving {
+    info!("Disabling job archiving");
+    transaction
+      .batch_execute(&format!(
+        "drop trigger _900_graphboard_archive on {}.jobs",
+        (*CONFIG).graphile_worker_schema
+      ))
+      .await?;
+  }
+
+  if !trigger_exists(&transaction, "job_queues", "_100_graphboard_pause").await? {
+    transaction
+      .batch_execute(&format!(
+        "create trigger _100_graphboard_pause before insert on {}.job_queues for each row execute \
+         procedure {}.pause_job_queue()",
+        (*CONFIG).graphile_worker_schema,
+        schema
+      ))
+      .await?;
+  }
+
+  transaction.commit().await?;
+  Ok(())
+}
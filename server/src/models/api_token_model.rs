@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Roles are ordered, each one granting the permissions of the previous ones.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+  Viewer,
+  Operator,
+  Admin,
+}
+
+impl Role {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Role::Viewer => "viewer",
+      Role::Operator => "operator",
+      Role::Admin => "admin",
+    }
+  }
+}
+
+impl Default for Role {
+  fn default() -> Self {
+    Role::Viewer
+  }
+}
+
+/// Restricts the jobs a role may act on with `*` and `?` glob patterns.
+/// `None` allows any value, jobs without queue are matched as an empty name.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct JobScope {
+  pub queue_patterns: Option<Vec<String>>,
+  pub task_patterns: Option<Vec<String>>,
+}
+
+fn glob_matches(pattern: &[char], value: &[char]) -> bool {
+  match pattern.split_first() {
+    None => value.is_empty(),
+    Some(('*', rest)) => (0..=value.len()).any(|skip| glob_matches(rest, &value[skip..])),
+    Some((expected, rest)) => match value.split_first() {
+      Some((actual, value)) => {
+        (*expected == '?' || expected == actual) && glob_matches(rest, value)
+      }
+      None => false,
+    },
+  }
+}
+
+fn any_pattern_matches(patterns: &Option<Vec<String>>, value: &str) -> bool {
+  let value: Vec<char> = value.chars().collect();
+  patterns.as_ref().map_or(true, |patterns| {
+    patterns
+      .iter()
+      .any(|pattern| glob_matches(&pattern.chars().collect::<Vec<char>>(), &value))
+  })
+}
+
+impl JobScope {
+  pub fn is_restricted(&self) -> bool {
+    self.queue_patterns.is_some() || self.task_patterns.is_some()
+  }
+
+  pub fn allows(&self, queue_name: Option<&str>, task_identifier: &str) -> bool {
+    any_pattern_matches(&self.queue_patterns, queue_name.unwrap_or_default())
+      && any_pattern_matches(&self.task_patterns, task_identifier)
+  }
+
+  /// A queue lock holds back every task of the queue, so tokens scoped to task
+  /// identifiers may not act on queues.
+  pub fn allows_queue(&self, queue_name: &str) -> bool {
+    self.task_patterns.is_none() && any_pattern_matches(&self.queue_patterns, queue_name)
+  }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct ApiToken {
+  pub id: i32,
+  pub name: String,
+  pub role: Role,
+  #[serde(flatten)]
+  pub scope: JobScope,
+  pub created_at: DateTime<Utc>,
+  pub last_used_at: Option<DateTime<Utc>>,
+  pub revoked_at: Option<DateTime<Utc>>,
+}
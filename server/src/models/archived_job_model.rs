@@ -0,0 +1,48 @@
+use crate::models::Job;
+use chrono::{DateTime, Utc};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+
+/// Why a job was deleted, recorded by the `archive_job` trigger.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DeletionReason {
+  Completed,
+  Failed,
+  Removed,
+}
+
+impl DeletionReason {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      DeletionReason::Completed => "completed",
+      DeletionReason::Failed => "failed",
+      DeletionReason::Removed => "removed",
+    }
+  }
+}
+
+/// A job deleted from the graphile worker schema. Its columns are kept as they
+/// were when deleted, so no state is derived from them.
+#[derive(Deserialize, Clone)]
+pub struct ArchivedJob {
+  #[serde(flatten)]
+  pub job: Job,
+  pub deletion_reason: DeletionReason,
+  pub archived_at: DateTime<Utc>,
+}
+
+impl AsRef<Job> for ArchivedJob {
+  fn as_ref(&self) -> &Job {
+    &self.job
+  }
+}
+
+impl Serialize for ArchivedJob {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut archived_job = serializer.serialize_struct("ArchivedJob", Job::COLUMN_COUNT + 2)?;
+    self.job.serialize_columns(&mut archived_job)?;
+    archived_job.serialize_field("deletionReason", &self.deletion_reason)?;
+    archived_job.serialize_field("archivedAt", &self.archived_at)?;
+    archived_job.end()
+  }
+}
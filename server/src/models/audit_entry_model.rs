@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct AuditEntry {
+  pub id: i64,
+  pub created_at: DateTime<Utc>,
+  pub actor_token_id: Option<i32>,
+  pub actor: String,
+  pub route: String,
+  pub body: Option<Value>,
+  pub job_ids: Vec<i64>,
+  pub before: Option<Value>,
+  pub after: Option<Value>,
+}
+
+pub struct AddAuditEntryData {
+  pub actor_token_id: Option<i32>,
+  pub actor: String,
+  pub route: String,
+  pub body: Value,
+  pub job_ids: Vec<i64>,
+  pub before: Option<Value>,
+  pub after: Value,
+}
@@ -1,9 +1,38 @@
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
 
-#[derive(Deserialize, Serialize, Clone)]
-#[serde(rename_all(serialize = "camelCase"))]
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+  Queued,
+  Scheduled,
+  Running,
+  Retrying,
+  Failed,
+}
+
+impl JobState {
+  pub const ALL: [JobState; 5] = [
+    JobState::Queued,
+    JobState::Scheduled,
+    JobState::Running,
+    JobState::Retrying,
+    JobState::Failed,
+  ];
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      JobState::Queued => "queued",
+      JobState::Scheduled => "scheduled",
+      JobState::Running => "running",
+      JobState::Retrying => "retrying",
+      JobState::Failed => "failed",
+    }
+  }
+}
+
+#[derive(Deserialize, Clone)]
 pub struct Job {
   pub id: i64,
   pub queue_name: Option<String>,
@@ -23,6 +52,84 @@ pub struct Job {
   pub flags: Option<Value>,
 }
 
+impl AsRef<Job> for Job {
+  fn as_ref(&self) -> &Job {
+    self
+  }
+}
+
+impl Job {
+  /// Derives the job state from its raw columns. The precedence must stay in
+  /// sync with `job_state_sql` in the job repository.
+  pub fn state(&self) -> JobState {
+    if self.locked_at.is_some() {
+      JobState::Running
+    } else if self.attempts >= self.max_attempts {
+      JobState::Failed
+    } else if self.attempts > 0 && self.last_error.is_some() {
+      JobState::Retrying
+    } else if self.run_at > Utc::now() {
+      JobState::Scheduled
+    } else {
+      JobState::Queued
+    }
+  }
+}
+
+impl Job {
+  pub const COLUMN_COUNT: usize = 16;
+
+  /// Serializes the job columns only, without the derived state.
+  pub fn serialize_columns<S: SerializeStruct>(&self, job: &mut S) -> Result<(), S::Error> {
+    job.serialize_field("id", &self.id)?;
+    job.serialize_field("queueName", &self.queue_name)?;
+    job.serialize_field("taskIdentifier", &self.task_identifier)?;
+    job.serialize_field("payload", &self.payload)?;
+    job.serialize_field("priority", &self.priority)?;
+    job.serialize_field("runAt", &self.run_at)?;
+    job.serialize_field("attempts", &self.attempts)?;
+    job.serialize_field("maxAttempts", &self.max_attempts)?;
+    job.serialize_field("lastError", &self.last_error)?;
+    job.serialize_field("createdAt", &self.created_at)?;
+    job.serialize_field("updatedAt", &self.updated_at)?;
+    job.serialize_field("key", &self.key)?;
+    job.serialize_field("lockedAt", &self.locked_at)?;
+    job.serialize_field("lockedBy", &self.locked_by)?;
+    job.serialize_field("revision", &self.revision)?;
+    job.serialize_field("flags", &self.flags)?;
+    Ok(())
+  }
+}
+
+impl Serialize for Job {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut job = serializer.serialize_struct("Job", Job::COLUMN_COUNT + 1)?;
+    self.serialize_columns(&mut job)?;
+    job.serialize_field("state", &self.state())?;
+    job.end()
+  }
+}
+
+/// Reads flags either as a list or as the `{"flag": true}` object jobs store
+/// them in, which is how they are exported.
+fn deserialize_flags<'de, D: Deserializer<'de>>(
+  deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error> {
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Flags {
+    List(Vec<String>),
+    Object(Map<String, Value>),
+  }
+
+  Ok(
+    Option::<Flags>::deserialize(deserializer)?.map(|flags| match flags {
+      Flags::List(flags) => flags,
+      Flags::Object(flags) => flags.into_iter().map(|(flag, _)| flag).collect(),
+    }),
+  )
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct AddJobData {
@@ -31,8 +138,143 @@ pub struct AddJobData {
   pub queue_name: Option<String>,
   pub run_at: Option<DateTime<Utc>>,
   pub max_attempts: Option<i32>,
+  /// Also read from `key`, so that exported jobs can be imported back
+  #[serde(alias = "key")]
   pub job_key: Option<String>,
   pub priority: Option<i32>,
+  #[serde(default, deserialize_with = "deserialize_flags")]
   pub flags: Option<Vec<String>>,
   pub job_key_mode: Option<String>,
 }
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateJobData {
+  pub revision: Option<i32>,
+  pub payload: Option<Value>,
+  pub queue_name: Option<String>,
+  pub task_identifier: Option<String>,
+  pub priority: Option<i32>,
+  pub max_attempts: Option<i32>,
+  #[serde(default, deserialize_with = "deserialize_flags")]
+  pub flags: Option<Vec<String>>,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::Duration;
+  use serde_json::json;
+
+  fn job() -> Job {
+    let now = Utc::now();
+    Job {
+      id: 1,
+      queue_name: None,
+      task_identifier: "task".to_string(),
+      payload: json!({}),
+      priority: 0,
+      run_at: now - Duration::seconds(1),
+      attempts: 0,
+      max_attempts: 25,
+      last_error: None,
+      created_at: now,
+      updated_at: now,
+      key: None,
+      locked_at: None,
+      locked_by: None,
+      revision: 0,
+      flags: None,
+    }
+  }
+
+  #[test]
+  fn queued() {
+    assert_eq!(job().state(), JobState::Queued);
+  }
+
+  #[test]
+  fn scheduled() {
+    let job = Job {
+      run_at: Utc::now() + Duration::hours(1),
+      ..job()
+    };
+    assert_eq!(job.state(), JobState::Scheduled);
+  }
+
+  #[test]
+  fn running() {
+    let job = Job {
+      attempts: 1,
+      locked_at: Some(Utc::now()),
+      locked_by: Some("worker".to_string()),
+      ..job()
+    };
+    assert_eq!(job.state(), JobState::Running);
+  }
+
+  #[test]
+  fn running_last_attempt() {
+    let job = Job {
+      attempts: 25,
+      last_error: Some("error".to_string()),
+      locked_at: Some(Utc::now()),
+      locked_by: Some("worker".to_string()),
+      ..job()
+    };
+    assert_eq!(job.state(), JobState::Running);
+  }
+
+  #[test]
+  fn retrying() {
+    let job = Job {
+      attempts: 1,
+      last_error: Some("error".to_string()),
+      run_at: Utc::now() + Duration::minutes(1),
+      ..job()
+    };
+    assert_eq!(job.state(), JobState::Retrying);
+  }
+
+  #[test]
+  fn failed() {
+    let job = Job {
+      attempts: 25,
+      last_error: Some("error".to_string()),
+      ..job()
+    };
+    assert_eq!(job.state(), JobState::Failed);
+  }
+
+  #[test]
+  fn exported_job_imports_back() {
+    let job = Job {
+      queue_name: Some("mail".to_string()),
+      key: Some("welcome".to_string()),
+      flags: Some(json!({ "slow": true, "bulk": true })),
+      ..job()
+    };
+    let data: AddJobData = serde_json::from_value(serde_json::to_value(&job).unwrap()).unwrap();
+    assert_eq!(data.task_identifier, "task");
+    assert_eq!(data.queue_name.as_deref(), Some("mail"));
+    assert_eq!(data.job_key.as_deref(), Some("welcome"));
+    assert_eq!(data.max_attempts, Some(25));
+    assert_eq!(
+      data.flags,
+      Some(vec!["bulk".to_string(), "slow".to_string()])
+    );
+  }
+
+  #[test]
+  fn flags_are_read_from_a_list() {
+    let data: AddJobData =
+      serde_json::from_value(json!({ "taskIdentifier": "task", "flags": ["slow"] })).unwrap();
+    assert_eq!(data.flags, Some(vec!["slow".to_string()]));
+    let data: AddJobData = serde_json::from_value(json!({ "taskIdentifier": "task" })).unwrap();
+    assert_eq!(data.flags, None);
+    assert!(serde_json::from_value::<AddJobData>(
+      json!({ "taskIdentifier": "task", "flags": "slow" })
+    )
+    .is_err());
+  }
+}
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+
+/// `locked_by` prefix of the job queues held back by a graphboard pause.
+pub const PAUSED_LOCK_PREFIX: &str = "graphboard:paused:";
+
+#[derive(Deserialize, Clone)]
+pub struct JobQueue {
+  pub queue_name: String,
+  pub job_count: i32,
+  pub locked_at: Option<DateTime<Utc>>,
+  pub locked_by: Option<String>,
+}
+
+impl JobQueue {
+  pub fn paused(&self) -> bool {
+    self
+      .locked_by
+      .as_deref()
+      .map_or(false, |locked_by| locked_by.starts_with(PAUSED_LOCK_PREFIX))
+  }
+}
+
+impl Serialize for JobQueue {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut job_queue = serializer.serialize_struct("JobQueue", 5)?;
+    job_queue.serialize_field("queueName", &self.queue_name)?;
+    job_queue.serialize_field("jobCount", &self.job_count)?;
+    job_queue.serialize_field("lockedAt", &self.locked_at)?;
+    job_queue.serialize_field("lockedBy", &self.locked_by)?;
+    job_queue.serialize_field("paused", &self.paused())?;
+    job_queue.end()
+  }
+}
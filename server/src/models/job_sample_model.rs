@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Job count of a task identifier and state over a time bucket.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct JobSamplePoint {
+  pub at: DateTime<Utc>,
+  pub task_identifier: String,
+  pub state: String,
+  /// Average over the samples taken in the bucket
+  pub count: f64,
+  pub max: f64,
+}
@@ -0,0 +1,10 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct KnownCrontab {
+  pub identifier: String,
+  pub known_since: DateTime<Utc>,
+  pub last_execution: Option<DateTime<Utc>>,
+}
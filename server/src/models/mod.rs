@@ -1,3 +1,19 @@
+mod api_token_model;
+mod archived_job_model;
+mod audit_entry_model;
 mod job_model;
+mod job_queue_model;
+mod job_sample_model;
+mod known_crontab_model;
+mod paused_queue_model;
+mod worker_model;
 
+pub use api_token_model::*;
+pub use archived_job_model::*;
+pub use audit_entry_model::*;
 pub use job_model::*;
+pub use job_queue_model::*;
+pub use job_sample_model::*;
+pub use known_crontab_model::*;
+pub use paused_queue_model::*;
+pub use worker_model::*;
@@ -0,0 +1,10 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct PausedQueue {
+  pub queue_name: String,
+  pub paused_by: String,
+  pub paused_at: DateTime<Utc>,
+}
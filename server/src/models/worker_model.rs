@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct WorkerJobLock {
+  pub id: i64,
+  pub task_identifier: String,
+  pub queue_name: Option<String>,
+  pub locked_at: DateTime<Utc>,
+  pub lock_age_seconds: f64,
+  /// Whether `get_job` would let another worker take the job again
+  pub expired: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct WorkerQueueLock {
+  pub queue_name: String,
+  pub locked_at: DateTime<Utc>,
+  pub lock_age_seconds: f64,
+  pub expired: bool,
+}
+
+/// A worker as seen through the locks it currently holds.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct Worker {
+  pub worker_id: String,
+  pub oldest_locked_at: DateTime<Utc>,
+  pub expired_lock_count: usize,
+  pub jobs: Vec<WorkerJobLock>,
+  pub queues: Vec<WorkerQueueLock>,
+}
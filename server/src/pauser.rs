@@ -0,0 +1,30 @@
+use crate::{config::CONFIG, repositories::lock_paused_job_queues};
+use actix_web::rt;
+use deadpool_postgres::Pool;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, error};
+
+/// Periodically puts the pause marker back on the paused job queues, in case
+/// their lock was cleared or is about to expire.
+pub fn start_pauser(pool: Pool) {
+  let period = Duration::from_secs((*CONFIG).pause.interval.max(1));
+
+  rt::spawn(async move {
+    let mut ticks = interval(period);
+    loop {
+      ticks.tick().await;
+      let result = match pool.get().await {
+        Ok(client) => lock_paused_job_queues(&**client, None).await,
+        Err(error) => Err(error.into()),
+      };
+      match result {
+        Ok(locked) => debug!(locked, "Paused job queues tick"),
+        Err(error) => error!(
+          error = format!("{:?}", error).as_str(),
+          "Error while locking paused job queues"
+        ),
+      }
+    }
+  });
+}
@@ -0,0 +1,122 @@
+use crate::{
+  models::{ApiToken, JobScope, Role},
+  repositories::RepositoryError,
+  CONFIG,
+};
+use deadpool_postgres::Client;
+use tokio_postgres::{GenericClient, Row};
+use tracing::error;
+
+impl TryFrom<Row> for ApiToken {
+  type Error = RepositoryError;
+
+  fn try_from(row: Row) -> Result<Self, Self::Error> {
+    let api_token_result = {
+      Ok(ApiToken {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        role: serde_json::from_value(row.try_get::<_, String>("role")?.into())?,
+        scope: JobScope {
+          queue_patterns: row.try_get("queue_patterns")?,
+          task_patterns: row.try_get("task_patterns")?,
+        },
+        created_at: row.try_get("created_at")?,
+        last_used_at: row.try_get("last_used_at")?,
+        revoked_at: row.try_get("revoked_at")?,
+      })
+    };
+    if let Err(error) = &api_token_result {
+      error!(
+        error = format!("{:?}", error).as_str(),
+        "Error while deserializing row to ApiToken"
+      );
+    }
+    api_token_result
+  }
+}
+
+pub async fn find_api_tokens(client: &Client) -> Result<Vec<ApiToken>, RepositoryError> {
+  let query = format!(
+    "select t.* from {}.api_tokens t order by t.id asc",
+    (*CONFIG).graphboard_schema
+  );
+
+  client
+    .query(&query, &[])
+    .await?
+    .into_iter()
+    .map(ApiToken::try_from)
+    .collect()
+}
+
+pub async fn create_api_token<C: GenericClient>(
+  client: &C,
+  name: &str,
+  role: Role,
+  scope: &JobScope,
+  token_hash: &str,
+) -> Result<ApiToken, RepositoryError> {
+  let query = format!(
+    r#"insert into {}.api_tokens (name, role, queue_patterns, task_patterns, token_hash)
+      values ($1, $2, $3, $4, $5) returning *"#,
+    (*CONFIG).graphboard_schema
+  );
+
+  client
+    .query_one(
+      &query,
+      &[
+        &name,
+        &role.as_str(),
+        &scope.queue_patterns,
+        &scope.task_patterns,
+        &token_hash,
+      ],
+    )
+    .await?
+    .try_into()
+}
+
+/// Revokes a token, keeping the original revocation date if it already was.
+pub async fn revoke_api_token<C: GenericClient>(
+  client: &C,
+  id: i32,
+) -> Result<ApiToken, RepositoryError> {
+  let query = format!(
+    "update {}.api_tokens t set revoked_at = coalesce(t.revoked_at, now()) where t.id = $1 \
+     returning t.*",
+    (*CONFIG).graphboard_schema
+  );
+
+  client
+    .query_opt(&query, &[&id])
+    .await?
+    .ok_or(RepositoryError::NotFound)?
+    .try_into()
+}
+
+/// Finds the active token matching `token_hash` and records its use.
+/// `last_used_at` is only written once a minute to spare a write per request.
+pub async fn authenticate_api_token(
+  client: &Client,
+  token_hash: &str,
+) -> Result<Option<ApiToken>, RepositoryError> {
+  let query = format!(
+    r#"with token as (
+        select t.* from {schema}.api_tokens t where t.token_hash = $1 and t.revoked_at is null
+      ), touched as (
+        update {schema}.api_tokens t set last_used_at = now()
+        from token
+        where t.id = token.id
+          and (token.last_used_at is null or token.last_used_at < now() - interval '1 minute')
+      )
+      select * from token"#,
+    schema = (*CONFIG).graphboard_schema
+  );
+
+  client
+    .query_opt(&query, &[&token_hash])
+    .await?
+    .map(ApiToken::try_from)
+    .transpose()
+}
@@ -0,0 +1,35 @@
+use crate::{
+  models::ArchivedJob,
+  repositories::{find_job_rows, CountMode, FindJobsParams, RepositoryError},
+  CONFIG,
+};
+use deadpool_postgres::Client;
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindArchivedJobsResult {
+  archived_jobs: Vec<ArchivedJob>,
+  count: Option<i64>,
+  count_mode: CountMode,
+  next_cursor: Option<String>,
+  previous_cursor: Option<String>,
+}
+
+/// Archived jobs accept the same params as `find_jobs`, but for the state
+/// filter: their columns are kept as they were when deleted.
+pub async fn find_archived_jobs(
+  client: &Client,
+  params: FindJobsParams,
+) -> Result<FindArchivedJobsResult, RepositoryError> {
+  let table = format!("{}.archived_jobs", (*CONFIG).graphboard_schema);
+  let page = find_job_rows(client, &table, params, false).await?;
+
+  Ok(FindArchivedJobsResult {
+    archived_jobs: page.rows,
+    count: page.count,
+    count_mode: page.count_mode,
+    next_cursor: page.next_cursor,
+    previous_cursor: page.previous_cursor,
+  })
+}
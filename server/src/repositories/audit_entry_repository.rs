@@ -0,0 +1,112 @@
+use crate::{
+  models::{AddAuditEntryData, AuditEntry},
+  repositories::{Pagination, RepositoryError, RepositoryPagination},
+  CONFIG,
+};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Client;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{GenericClient, Row};
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FindAuditEntriesFilters {
+  actor: Option<String>,
+  route: Option<String>,
+  job_id: Option<i64>,
+  from: Option<DateTime<Utc>>,
+  to: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FindAuditEntriesParams {
+  pub pagination: Option<RepositoryPagination>,
+  pub filters: Option<FindAuditEntriesFilters>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FindAuditEntriesResult {
+  audit_entries: Vec<AuditEntry>,
+  count: i64,
+}
+
+impl TryFrom<Row> for FindAuditEntriesResult {
+  type Error = RepositoryError;
+
+  fn try_from(row: Row) -> Result<Self, Self::Error> {
+    Ok(FindAuditEntriesResult {
+      audit_entries: serde_json::from_str(row.try_get("audit_entries")?)?,
+      count: row.try_get("count")?,
+    })
+  }
+}
+
+/// Newest entries come first.
+pub async fn find_audit_entries(
+  client: &Client,
+  params: FindAuditEntriesParams,
+) -> Result<FindAuditEntriesResult, RepositoryError> {
+  let query = format!(
+    r#"select a.* from {}.audit_log a
+        where ($1::text is null or a.actor ilike concat('%', $1::text, '%')) and
+              ($2::text is null or a.route ilike concat('%', $2::text, '%')) and
+              ($3::bigint is null or a.job_ids @> array[$3::bigint]) and
+              ($4::timestamptz is null or a.created_at >= $4::timestamptz) and
+              ($5::timestamptz is null or a.created_at < $5::timestamptz)"#,
+    (*CONFIG).graphboard_schema
+  );
+
+  let stmt = format!(
+    "select coalesce((select json_agg(data)::text from ({query} order by a.id desc limit {} \
+     offset {}) data), '[]') as audit_entries, coalesce((select count(c.*) from ({query}) c), 0) \
+     as count",
+    params.pagination.limit(),
+    params.pagination.offset(),
+    query = query,
+  );
+
+  let filters = params.filters.unwrap_or_default();
+  let result = client
+    .query_one(
+      stmt.as_str(),
+      &[
+        &filters.actor,
+        &filters.route,
+        &filters.job_id,
+        &filters.from,
+        &filters.to,
+      ],
+    )
+    .await?
+    .try_into()?;
+  Ok(result)
+}
+
+pub async fn add_audit_entry<C: GenericClient>(
+  client: &C,
+  data: AddAuditEntryData,
+) -> Result<(), RepositoryError> {
+  let query = format!(
+    r#"insert into {}.audit_log (actor_token_id, actor, route, body, job_ids, before, after)
+      values ($1, $2, $3, $4, $5, $6, $7)"#,
+    (*CONFIG).graphboard_schema
+  );
+
+  client
+    .execute(
+      &query,
+      &[
+        &data.actor_token_id,
+        &data.actor,
+        &data.route,
+        &data.body,
+        &data.job_ids,
+        &data.before,
+        &data.after,
+      ],
+    )
+    .await?;
+  Ok(())
+}
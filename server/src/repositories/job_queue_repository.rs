@@ -0,0 +1,217 @@
+use crate::{
+  models::{JobQueue, JobScope, PAUSED_LOCK_PREFIX},
+  repositories::{
+    like_patterns, Order, Pagination, RepositoryError, RepositoryOrder, RepositoryPagination,
+    ToSqlIdent,
+  },
+  CONFIG,
+};
+use deadpool_postgres::Client;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{GenericClient, Row};
+use tracing::error;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum JobQueueOrderField {
+  QueueName,
+  JobCount,
+  LockedAt,
+}
+
+impl ToSqlIdent for JobQueueOrderField {
+  fn sql_ident(&self) -> String {
+    match self {
+      JobQueueOrderField::QueueName => String::from("queue_name"),
+      JobQueueOrderField::JobCount => String::from("job_count"),
+      JobQueueOrderField::LockedAt => String::from("locked_at"),
+    }
+  }
+}
+
+impl Default for JobQueueOrderField {
+  fn default() -> Self {
+    JobQueueOrderField::QueueName
+  }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FindJobQueuesFilters {
+  queue_name: Option<String>,
+  locked: Option<bool>,
+  paused: Option<bool>,
+}
+
+impl Default for FindJobQueuesFilters {
+  fn default() -> Self {
+    FindJobQueuesFilters {
+      queue_name: None,
+      locked: None,
+      paused: None,
+    }
+  }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FindJobQueuesParams {
+  pub order: Option<RepositoryOrder<JobQueueOrderField>>,
+  pub pagination: Option<RepositoryPagination>,
+  pub filters: Option<FindJobQueuesFilters>,
+}
+
+impl FindJobQueuesParams {
+  pub fn filters(self) -> FindJobQueuesFilters {
+    self.filters.unwrap_or(FindJobQueuesFilters::default())
+  }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FindJobQueuesResult {
+  job_queues: Vec<JobQueue>,
+  count: i64,
+}
+
+impl TryFrom<Row> for FindJobQueuesResult {
+  type Error = RepositoryError;
+
+  fn try_from(row: Row) -> Result<Self, Self::Error> {
+    Ok(FindJobQueuesResult {
+      job_queues: serde_json::from_str(row.try_get("job_queues")?)?,
+      count: row.try_get("count")?,
+    })
+  }
+}
+
+impl TryFrom<Row> for JobQueue {
+  type Error = RepositoryError;
+
+  fn try_from(row: Row) -> Result<Self, Self::Error> {
+    let job_queue_result = {
+      Ok(JobQueue {
+        queue_name: row.try_get("queue_name")?,
+        job_count: row.try_get("job_count")?,
+        locked_at: row.try_get("locked_at")?,
+        locked_by: row.try_get("locked_by")?,
+      })
+    };
+    if let Err(error) = &job_queue_result {
+      error!(
+        error = format!("{:?}", error).as_str(),
+        "Error while deserializing row to JobQueue"
+      );
+    }
+    job_queue_result
+  }
+}
+
+/// Finds the job queues `scope` allows, see `JobScope::allows_queue`.
+pub async fn find_job_queues(
+  client: &Client,
+  params: FindJobQueuesParams,
+  scope: &JobScope,
+) -> Result<FindJobQueuesResult, RepositoryError> {
+  let query = format!(
+    r#"select q.* from {}.job_queues q
+        where ($1::text is null or $1::text = '' or q.queue_name ilike concat('%', $1::text, '%')) and
+              ($2::boolean is null or (q.locked_at is not null) = $2::boolean) and
+              ($3::boolean is null or coalesce(q.locked_by like '{}%', false) = $3::boolean) and
+              ($4::text[] is null or q.queue_name like any($4::text[]))"#,
+    (*CONFIG).graphile_worker_schema,
+    PAUSED_LOCK_PREFIX
+  );
+
+  let stmt = format!(
+    "select coalesce((select json_agg(data)::text from ({query} order by {} limit {} offset {}) \
+     data), '[]') as job_queues, coalesce((select count(c.*) from ({query}) c), 0) as count",
+    params.order.order(),
+    params.pagination.limit(),
+    params.pagination.offset(),
+    query = query,
+  );
+
+  // Tokens scoped to task identifiers see no queue at all
+  let queue_patterns = match (&scope.queue_patterns, &scope.task_patterns) {
+    (_, Some(_)) => Some(Vec::new()),
+    (Some(queue_patterns), None) => Some(like_patterns(queue_patterns)),
+    (None, None) => None,
+  };
+  let filters = params.filters();
+  let result = client
+    .query_one(
+      stmt.as_str(),
+      &[
+        &filters.queue_name,
+        &filters.locked,
+        &filters.paused,
+        &queue_patterns,
+      ],
+    )
+    .await?
+    .try_into()?;
+  Ok(result)
+}
+
+/// Finds the locked queues among `queue_names`, or held by `locked_by`, whose
+/// lock is at least `min_lock_age` seconds old. Paused queues are left alone.
+pub async fn find_locked_job_queues<C: GenericClient>(
+  client: &C,
+  queue_names: Option<&[String]>,
+  locked_by: Option<&str>,
+  min_lock_age: i64,
+) -> Result<Vec<JobQueue>, RepositoryError> {
+  let query = format!(
+    "select q.* from {}.job_queues q where q.locked_at < now() - $3::bigint * interval '1 second' \
+     and (q.queue_name = any($1::text[]) or q.locked_by = $2::text) and q.locked_by not like \
+     '{}%' order by q.queue_name asc",
+    (*CONFIG).graphile_worker_schema,
+    PAUSED_LOCK_PREFIX
+  );
+
+  client
+    .query(&query, &[&queue_names, &locked_by, &min_lock_age])
+    .await?
+    .into_iter()
+    .map(JobQueue::try_from)
+    .collect()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockJobQueuesResult {
+  pub unlocked_job_queues: Vec<JobQueue>,
+}
+
+impl TryFrom<Row> for UnlockJobQueuesResult {
+  type Error = RepositoryError;
+
+  fn try_from(row: Row) -> Result<Self, Self::Error> {
+    Ok(UnlockJobQueuesResult {
+      unlocked_job_queues: serde_json::from_str(row.try_get("unlocked_job_queues")?)?,
+    })
+  }
+}
+
+pub async fn unlock_job_queues<C: GenericClient, N: AsRef<[String]>>(
+  client: &C,
+  queue_names: N,
+  min_lock_age: i64,
+) -> Result<UnlockJobQueuesResult, RepositoryError> {
+  let query = format!(
+    "with u as (update {}.job_queues set locked_at = null, locked_by = null where queue_name = \
+     any($1::text[]) and locked_at < now() - $2::bigint * interval '1 second' and locked_by not \
+     like '{}%' returning *) select coalesce(json_agg(u order by u.queue_name), '[]')::text \
+     unlocked_job_queues from u",
+    (*CONFIG).graphile_worker_schema,
+    PAUSED_LOCK_PREFIX
+  );
+
+  let result = client
+    .query_one(&query, &[&queue_names.as_ref(), &min_lock_age])
+    .await?
+    .try_into()?;
+
+  Ok(result)
+}
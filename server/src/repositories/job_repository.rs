@@ -1,28 +1,80 @@
 use crate::{
-  models::{AddJobData, Job},
+  models::{AddJobData, DeletionReason, Job, JobScope, JobState, UpdateJobData},
   repositories::{
-    Order, Pagination, RepositoryError, RepositoryOrder, RepositoryPagination, ToSqlIdent,
+    like_patterns, CountMode, Keyset, KeysetField, Pagination, RepositoryCursor, RepositoryError,
+    RepositoryOrderList, RepositoryPagination, SqlParams, ToSqlIdent,
   },
   CONFIG,
 };
-use chrono::{DateTime, Utc};
-use deadpool_postgres::Client;
-use serde::{Deserialize, Serialize};
-use tokio_postgres::Row;
+use chrono::{DateTime, SecondsFormat, Utc};
+use deadpool_postgres::{Client, Transaction};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc::Sender;
+use tokio_postgres::{types::ToSql, GenericClient, Row};
 use tracing::error;
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum JobOrderField {
+  Id,
+  QueueName,
   TaskIdentifier,
+  Priority,
   RunAt,
+  Attempts,
+  MaxAttempts,
+  CreatedAt,
+  UpdatedAt,
+  LockedAt,
 }
 
 impl ToSqlIdent for JobOrderField {
   fn sql_ident(&self) -> String {
     match self {
+      JobOrderField::Id => String::from("id"),
+      JobOrderField::QueueName => String::from("queue_name"),
       JobOrderField::TaskIdentifier => String::from("task_identifier"),
+      JobOrderField::Priority => String::from("priority"),
       JobOrderField::RunAt => String::from("run_at"),
+      JobOrderField::Attempts => String::from("attempts"),
+      JobOrderField::MaxAttempts => String::from("max_attempts"),
+      JobOrderField::CreatedAt => String::from("created_at"),
+      JobOrderField::UpdatedAt => String::from("updated_at"),
+      JobOrderField::LockedAt => String::from("locked_at"),
+    }
+  }
+}
+
+fn timestamp_cursor_value(timestamp: &DateTime<Utc>) -> String {
+  timestamp.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+impl KeysetField<Job> for JobOrderField {
+  fn sql_type(&self) -> &'static str {
+    match self {
+      JobOrderField::Id => "bigint",
+      JobOrderField::QueueName | JobOrderField::TaskIdentifier => "text",
+      JobOrderField::Priority | JobOrderField::Attempts | JobOrderField::MaxAttempts => "integer",
+      JobOrderField::RunAt
+      | JobOrderField::CreatedAt
+      | JobOrderField::UpdatedAt
+      | JobOrderField::LockedAt => "timestamptz",
+    }
+  }
+
+  fn cursor_value(&self, job: &Job) -> Option<String> {
+    match self {
+      JobOrderField::Id => Some(job.id.to_string()),
+      JobOrderField::QueueName => job.queue_name.clone(),
+      JobOrderField::TaskIdentifier => Some(job.task_identifier.clone()),
+      JobOrderField::Priority => Some(job.priority.to_string()),
+      JobOrderField::RunAt => Some(timestamp_cursor_value(&job.run_at)),
+      JobOrderField::Attempts => Some(job.attempts.to_string()),
+      JobOrderField::MaxAttempts => Some(job.max_attempts.to_string()),
+      JobOrderField::CreatedAt => Some(timestamp_cursor_value(&job.created_at)),
+      JobOrderField::UpdatedAt => Some(timestamp_cursor_value(&job.updated_at)),
+      JobOrderField::LockedAt => job.locked_at.as_ref().map(timestamp_cursor_value),
     }
   }
 }
@@ -33,11 +85,115 @@ impl Default for JobOrderField {
   }
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
+pub enum PayloadOperator {
+  Eq,
+  NotEq,
+  Lt,
+  Lte,
+  Gt,
+  Gte,
+}
+
+impl PayloadOperator {
+  // Longer operators first so that `>=` is not read as `>`
+  const TOKENS: [(&'static str, PayloadOperator); 7] = [
+    ("!=", PayloadOperator::NotEq),
+    ("<>", PayloadOperator::NotEq),
+    ("<=", PayloadOperator::Lte),
+    (">=", PayloadOperator::Gte),
+    ("=", PayloadOperator::Eq),
+    ("<", PayloadOperator::Lt),
+    (">", PayloadOperator::Gt),
+  ];
+
+  fn sql(&self) -> &'static str {
+    match self {
+      PayloadOperator::Eq => "=",
+      PayloadOperator::NotEq => "<>",
+      PayloadOperator::Lt => "<",
+      PayloadOperator::Lte => "<=",
+      PayloadOperator::Gt => ">",
+      PayloadOperator::Gte => ">=",
+    }
+  }
+}
+
+/// A comparison on a value of the job payload, written as
+/// `payload.some.path = <json value>`. Values that are not valid JSON are
+/// compared as strings.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct PayloadPredicate {
+  raw: String,
+  path: Vec<String>,
+  operator: PayloadOperator,
+  value: Value,
+}
+
+impl TryFrom<String> for PayloadPredicate {
+  type Error = String;
+
+  fn try_from(raw: String) -> Result<Self, Self::Error> {
+    let (position, token, operator) = PayloadOperator::TOKENS
+      .iter()
+      .filter_map(|(token, operator)| {
+        raw
+          .find(token)
+          .map(|position| (position, *token, *operator))
+      })
+      .min_by_key(|(position, token, _)| (*position, usize::MAX - token.len()))
+      .ok_or_else(|| format!("missing comparison operator in payload filter '{}'", raw))?;
+
+    let raw_path = raw[..position].trim();
+    let raw_path = raw_path.strip_prefix("payload.").unwrap_or(raw_path);
+    let path: Vec<String> = raw_path
+      .split('.')
+      .map(|segment| String::from(segment.trim()))
+      .collect();
+    if path.iter().any(String::is_empty) {
+      return Err(format!("invalid payload path '{}'", raw_path));
+    }
+
+    let raw_value = raw[position + token.len()..].trim();
+    let value = serde_json::from_str(raw_value).unwrap_or_else(|_| Value::from(raw_value));
+
+    Ok(PayloadPredicate {
+      raw: raw.clone(),
+      path,
+      operator,
+      value,
+    })
+  }
+}
+
+impl From<PayloadPredicate> for String {
+  fn from(predicate: PayloadPredicate) -> Self {
+    predicate.raw
+  }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct FindJobsFilters {
   task_identifier: Option<String>,
   queue_name: Option<String>,
+  state: Option<Vec<JobState>>,
+  run_at_from: Option<DateTime<Utc>>,
+  run_at_to: Option<DateTime<Utc>>,
+  created_at_from: Option<DateTime<Utc>>,
+  created_at_to: Option<DateTime<Utc>>,
+  attempts_min: Option<i32>,
+  attempts_max: Option<i32>,
+  last_error: Option<String>,
+  locked: Option<bool>,
+  locked_by: Option<String>,
+  key_prefix: Option<String>,
+  flags: Option<Vec<String>>,
+  payload: Option<PayloadPredicate>,
+  /// Set from the principal by the services, never read from the query
+  #[serde(skip)]
+  scope: JobScope,
 }
 
 impl Default for FindJobsFilters {
@@ -45,39 +201,233 @@ impl Default for FindJobsFilters {
     FindJobsFilters {
       task_identifier: None,
       queue_name: None,
+      state: None,
+      run_at_from: None,
+      run_at_to: None,
+      created_at_from: None,
+      created_at_to: None,
+      attempts_min: None,
+      attempts_max: None,
+      last_error: None,
+      locked: None,
+      locked_by: None,
+      key_prefix: None,
+      flags: None,
+      payload: None,
+      scope: JobScope::default(),
+    }
+  }
+}
+
+fn non_empty(value: &Option<String>) -> Option<&String> {
+  value.as_ref().filter(|value| !value.is_empty())
+}
+
+impl FindJobsFilters {
+  pub fn without_state(&self) -> FindJobsFilters {
+    FindJobsFilters {
+      state: None,
+      ..self.clone()
+    }
+  }
+
+  pub fn has_state(&self) -> bool {
+    self.state.is_some()
+  }
+
+  /// Restricts the matched jobs to the ones `scope` allows.
+  pub fn scoped(self, scope: &JobScope) -> FindJobsFilters {
+    FindJobsFilters {
+      scope: scope.clone(),
+      ..self
+    }
+  }
+
+  /// Builds the conditions matching these filters against the jobs table
+  /// aliased as `alias`, binding every value into `params`.
+  pub fn where_clause(&self, alias: &str, params: &mut SqlParams) -> String {
+    let mut conditions = vec![String::from("true")];
+
+    if let Some(task_identifier) = non_empty(&self.task_identifier) {
+      conditions.push(format!(
+        "{}.task_identifier ilike concat('%', {}::text, '%')",
+        alias,
+        params.push(task_identifier.clone())
+      ));
+    }
+    if let Some(queue_name) = non_empty(&self.queue_name) {
+      conditions.push(format!(
+        "{}.queue_name ilike concat('%', {}::text, '%')",
+        alias,
+        params.push(queue_name.clone())
+      ));
+    }
+    if let Some(states) = &self.state {
+      let states: Vec<&str> = states.iter().map(JobState::as_str).collect();
+      conditions.push(format!(
+        "{} = any({}::text[])",
+        job_state_sql(alias),
+        params.push(states)
+      ));
+    }
+    if let Some(run_at_from) = self.run_at_from {
+      conditions.push(format!(
+        "{}.run_at >= {}::timestamptz",
+        alias,
+        params.push(run_at_from)
+      ));
+    }
+    if let Some(run_at_to) = self.run_at_to {
+      conditions.push(format!(
+        "{}.run_at < {}::timestamptz",
+        alias,
+        params.push(run_at_to)
+      ));
+    }
+    if let Some(created_at_from) = self.created_at_from {
+      conditions.push(format!(
+        "{}.created_at >= {}::timestamptz",
+        alias,
+        params.push(created_at_from)
+      ));
+    }
+    if let Some(created_at_to) = self.created_at_to {
+      conditions.push(format!(
+        "{}.created_at < {}::timestamptz",
+        alias,
+        params.push(created_at_to)
+      ));
+    }
+    if let Some(attempts_min) = self.attempts_min {
+      conditions.push(format!(
+        "{}.attempts >= {}::integer",
+        alias,
+        params.push(attempts_min)
+      ));
+    }
+    if let Some(attempts_max) = self.attempts_max {
+      conditions.push(format!(
+        "{}.attempts <= {}::integer",
+        alias,
+        params.push(attempts_max)
+      ));
+    }
+    if let Some(last_error) = non_empty(&self.last_error) {
+      conditions.push(format!(
+        "{}.last_error ilike concat('%', {}::text, '%')",
+        alias,
+        params.push(last_error.clone())
+      ));
+    }
+    if let Some(locked) = self.locked {
+      conditions.push(format!(
+        "({}.locked_at is not null) = {}::boolean",
+        alias,
+        params.push(locked)
+      ));
+    }
+    if let Some(locked_by) = non_empty(&self.locked_by) {
+      conditions.push(format!(
+        "{}.locked_by = {}::text",
+        alias,
+        params.push(locked_by.clone())
+      ));
+    }
+    if let Some(key_prefix) = non_empty(&self.key_prefix) {
+      conditions.push(format!(
+        "starts_with({}.key, {}::text)",
+        alias,
+        params.push(key_prefix.clone())
+      ));
     }
+    if let Some(flags) = self.flags.as_ref().filter(|flags| !flags.is_empty()) {
+      conditions.push(format!(
+        "{}.flags ?& {}::text[]",
+        alias,
+        params.push(flags.clone())
+      ));
+    }
+    if let Some(payload) = &self.payload {
+      conditions.push(format!(
+        "({}.payload::jsonb #> {}::text[]) {} {}::jsonb",
+        alias,
+        params.push(payload.path.clone()),
+        payload.operator.sql(),
+        params.push(payload.value.clone())
+      ));
+    }
+    if let Some(queue_patterns) = &self.scope.queue_patterns {
+      conditions.push(format!(
+        "coalesce({}.queue_name, '') like any({}::text[])",
+        alias,
+        params.push(like_patterns(queue_patterns))
+      ));
+    }
+    if let Some(task_patterns) = &self.scope.task_patterns {
+      conditions.push(format!(
+        "{}.task_identifier like any({}::text[])",
+        alias,
+        params.push(like_patterns(task_patterns))
+      ));
+    }
+
+    conditions.join(" and ")
   }
 }
 
+/// SQL counterpart of `Job::state`, evaluated against the jobs table aliased
+/// as `alias`.
+pub fn job_state_sql(alias: &str) -> String {
+  format!(
+    "(case when {a}.locked_at is not null then 'running' when {a}.attempts >= {a}.max_attempts \
+     then 'failed' when {a}.attempts > 0 and {a}.last_error is not null then 'retrying' when \
+     {a}.run_at > now() then 'scheduled' else 'queued' end)",
+    a = alias
+  )
+}
+
 #[derive(Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct FindJobsParams {
-  pub order: Option<RepositoryOrder<JobOrderField>>,
+  pub order: Option<RepositoryOrderList<JobOrderField>>,
   pub pagination: Option<RepositoryPagination>,
   pub filters: Option<FindJobsFilters>,
+  pub after: Option<String>,
+  pub before: Option<String>,
+  pub count: Option<CountMode>,
 }
 
 impl FindJobsParams {
-  pub fn filters(self) -> FindJobsFilters {
-    self.filters.unwrap_or(FindJobsFilters::default())
+  pub fn filters(&self) -> FindJobsFilters {
+    self.filters.clone().unwrap_or(FindJobsFilters::default())
   }
+
+  pub fn scoped(self, scope: &JobScope) -> FindJobsParams {
+    FindJobsParams {
+      filters: Some(self.filters().scoped(scope)),
+      ..self
+    }
+  }
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct JobStateCounts {
+  queued: i64,
+  scheduled: i64,
+  running: i64,
+  retrying: i64,
+  failed: i64,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct FindJobsResult {
   jobs: Vec<Job>,
-  count: i64,
-}
-
-impl TryFrom<Row> for FindJobsResult {
-  type Error = RepositoryError;
-
-  fn try_from(row: Row) -> Result<Self, Self::Error> {
-    Ok(FindJobsResult {
-      jobs: serde_json::from_str(row.try_get("jobs")?)?,
-      count: row.try_get("count")?,
-    })
-  }
+  count: Option<i64>,
+  count_mode: CountMode,
+  state_counts: Option<JobStateCounts>,
+  next_cursor: Option<String>,
+  previous_cursor: Option<String>,
 }
 
 impl TryFrom<Row> for Job {
@@ -114,63 +464,415 @@ impl TryFrom<Row> for Job {
   }
 }
 
+/// Sort keys of `order`, always ending with `id` so that the order is total.
+pub fn job_keyset(order: &RepositoryOrderList<JobOrderField>) -> Keyset<'_, Job> {
+  let mut keyset = Keyset::new();
+  for order in order.orders() {
+    keyset = keyset.push(order.field(), order.is_descending());
+  }
+  if order
+    .orders()
+    .iter()
+    .all(|order| *order.field() != JobOrderField::Id)
+  {
+    keyset = keyset.push(&JobOrderField::Id, false);
+  }
+  keyset
+}
+
+/// A page of rows from a table holding the job columns, see `find_job_rows`.
+pub struct JobRowsPage<T> {
+  pub rows: Vec<T>,
+  pub count: Option<i64>,
+  pub count_mode: CountMode,
+  pub state_counts: Option<JobStateCounts>,
+  pub next_cursor: Option<String>,
+  pub previous_cursor: Option<String>,
+}
+
+/// Finds rows of `table` with the jobs filters, ordering and pagination.
+/// State counts are only computed when `with_state_counts` is set.
+pub async fn find_job_rows<T: DeserializeOwned + AsRef<Job>>(
+  client: &Client,
+  table: &str,
+  params: FindJobsParams,
+  with_state_counts: bool,
+) -> Result<JobRowsPage<T>, RepositoryError> {
+  let filters = params.filters();
+  let mut sql_params = SqlParams::default();
+  let clause = filters.where_clause("j", &mut sql_params);
+  let query = format!(
+    "select j.* from {table} j where {clause}",
+    table = table,
+    clause = clause,
+  );
+
+  let order = params.order.clone().unwrap_or_default();
+  let keyset = job_keyset(&order);
+  let limit = params.pagination.limit();
+
+  let (cursor, backward) = match (&params.after, &params.before) {
+    (Some(after), _) => (Some(RepositoryCursor::decode(after)?), false),
+    (None, Some(before)) => (Some(RepositoryCursor::decode(before)?), true),
+    (None, None) => (None, false),
+  };
+  // One extra row is fetched to know whether another page follows
+  let page_query = match &cursor {
+    Some(cursor) => {
+      let predicate = keyset.predicate("p", cursor, backward, &mut sql_params)?;
+      format!(
+        "select p.* from ({query}) p where {predicate} order by {order} limit {limit}",
+        query = query,
+        predicate = predicate,
+        order = keyset.order(backward),
+        limit = limit + 1,
+      )
+    }
+    None => format!(
+      "{query} order by {order} limit {limit} offset {offset}",
+      query = query,
+      order = keyset.order(false),
+      limit = limit + 1,
+      offset = params.pagination.offset(),
+    ),
+  };
+
+  let count_mode = params.count.unwrap_or_default();
+  let count_query = match count_mode {
+    CountMode::Exact => format!(
+      "coalesce((select count(c.*) from ({query}) c), 0)",
+      query = query
+    ),
+    CountMode::Estimated => format!(
+      "(select greatest(reltuples, 0)::bigint from pg_class where oid = '{}'::regclass)",
+      table
+    ),
+    CountMode::Omitted => String::from("null::bigint"),
+  };
+  // Counting every state needs a full scan, so it is only done for exact counts
+  let state_counts_query = match count_mode {
+    CountMode::Exact if with_state_counts => format!(
+      "(select row_to_json(s)::text from (select {state_counts} from (select {state} as state \
+       from {table} j where {base_clause}) b) s)",
+      state_counts = JobState::ALL
+        .iter()
+        .map(|state| format!(
+          "count(*) filter (where b.state = '{0}') as {0}",
+          state.as_str()
+        ))
+        .collect::<Vec<_>>()
+        .join(", "),
+      state = job_state_sql("j"),
+      table = table,
+      base_clause = filters.without_state().where_clause("j", &mut sql_params),
+    ),
+    _ => String::from("null::text"),
+  };
+
+  let stmt = format!(
+    "select coalesce((select json_agg(data)::text from ({page_query}) data), '[]') as rows, \
+     {count_query} as count, {state_counts_query} as state_counts",
+    page_query = page_query,
+    count_query = count_query,
+    state_counts_query = state_counts_query,
+  );
+
+  let row = client
+    .query_one(stmt.as_str(), &sql_params.as_refs())
+    .await?;
+  let mut rows: Vec<T> = serde_json::from_str(row.try_get("rows")?)?;
+  let has_more = rows.len() as u64 > limit;
+  rows.truncate(limit as usize);
+  if backward {
+    rows.reverse();
+  }
+
+  let has_next = if backward { cursor.is_some() } else { has_more };
+  let has_previous = if backward {
+    has_more
+  } else {
+    cursor.is_some() || params.pagination.offset() > 0
+  };
+  let state_counts: Option<&str> = row.try_get("state_counts")?;
+
+  Ok(JobRowsPage {
+    next_cursor: rows
+      .last()
+      .filter(|_| has_next)
+      .map(|job| keyset.cursor(job.as_ref()).encode()),
+    previous_cursor: rows
+      .first()
+      .filter(|_| has_previous)
+      .map(|job| keyset.cursor(job.as_ref()).encode()),
+    count: row.try_get("count")?,
+    count_mode,
+    state_counts: state_counts.map(serde_json::from_str).transpose()?,
+    rows,
+  })
+}
+
 pub async fn find_jobs(
   client: &Client,
   params: FindJobsParams,
 ) -> Result<FindJobsResult, RepositoryError> {
+  let table = format!("{}.jobs", (*CONFIG).graphile_worker_schema);
+  let page = find_job_rows(client, &table, params, true).await?;
+
+  Ok(FindJobsResult {
+    jobs: page.rows,
+    count: page.count,
+    count_mode: page.count_mode,
+    state_counts: page.state_counts,
+    next_cursor: page.next_cursor,
+    previous_cursor: page.previous_cursor,
+  })
+}
+
+/// Sends every job matching `filters`, in `order`, to `batches`. Rows are read
+/// from a portal `batch_size` at a time, so memory stays bounded whatever the
+/// number of jobs. Stops early once the receiver is dropped.
+pub async fn stream_jobs(
+  client: &mut tokio_postgres::Client,
+  filters: &FindJobsFilters,
+  order: &RepositoryOrderList<JobOrderField>,
+  batch_size: i32,
+  batches: &Sender<Result<Vec<Job>, RepositoryError>>,
+) -> Result<(), RepositoryError> {
+  let mut sql_params = SqlParams::default();
   let query = format!(
-    r#"select j.* from {}.jobs j
-        where ($1::text is null or $1::text = '' or j.task_identifier ilike concat('%', $1::text, '%')) and
-              ($2::text is null or $2::text = '' or j.queue_name ilike concat('%', $2::text, '%'))"#,
-    (*CONFIG).graphile_worker_schema
+    "select j.* from {}.jobs j where {} order by {}",
+    (*CONFIG).graphile_worker_schema,
+    filters.where_clause("j", &mut sql_params),
+    job_keyset(order).order(false)
   );
 
-  let stmt = format!(
-    "select coalesce((select json_agg(data)::text from ({query} order by {} limit {} offset {}) \
-     data), '[]') as jobs, coalesce((select count(c.*) from ({query}) c), 0) as count",
-    params.order.order(),
-    params.pagination.limit(),
-    params.pagination.offset(),
-    query = query,
+  let transaction = client.transaction().await?;
+  let statement = transaction.prepare(&query).await?;
+  let portal = transaction.bind(&statement, &sql_params.as_refs()).await?;
+  loop {
+    let rows = transaction.query_portal(&portal, batch_size).await?;
+    let last_batch = (rows.len() as i32) < batch_size;
+    let jobs = rows
+      .into_iter()
+      .map(Job::try_from)
+      .collect::<Result<Vec<Job>, _>>()?;
+    if (!jobs.is_empty() && batches.send(Ok(jobs)).await.is_err()) || last_batch {
+      break;
+    }
+  }
+  transaction.commit().await?;
+
+  Ok(())
+}
+
+pub async fn count_jobs<C: GenericClient>(
+  client: &C,
+  filters: &FindJobsFilters,
+) -> Result<i64, RepositoryError> {
+  let mut sql_params = SqlParams::default();
+  let query = format!(
+    "select count(*) as count from {}.jobs j where {}",
+    (*CONFIG).graphile_worker_schema,
+    filters.where_clause("j", &mut sql_params)
   );
 
-  let filters = params.filters();
-  let result = client
-    .query_one(
-      stmt.as_str(),
-      &[&filters.task_identifier, &filters.queue_name],
-    )
+  let count = client
+    .query_one(query.as_str(), &sql_params.as_refs())
+    .await?
+    .try_get("count")?;
+  Ok(count)
+}
+
+pub async fn find_job_ids<C: GenericClient>(
+  client: &C,
+  filters: &FindJobsFilters,
+  limit: i64,
+) -> Result<Vec<i64>, RepositoryError> {
+  let mut sql_params = SqlParams::default();
+  let query = format!(
+    "select j.id from {}.jobs j where {} order by j.id limit {}",
+    (*CONFIG).graphile_worker_schema,
+    filters.where_clause("j", &mut sql_params),
+    limit
+  );
+
+  client
+    .query(query.as_str(), &sql_params.as_refs())
     .await?
+    .into_iter()
+    .map(|row| row.try_get("id").map_err(RepositoryError::from))
+    .collect()
+}
+
+pub async fn find_job_by_id<C: GenericClient>(
+  client: &C,
+  job_id: i64,
+) -> Result<Job, RepositoryError> {
+  let query = format!(
+    "select j.* from {}.jobs j where j.id = $1::bigint",
+    (*CONFIG).graphile_worker_schema
+  );
+
+  let job = client
+    .query_opt(&query, &[&job_id])
+    .await?
+    .ok_or(RepositoryError::NotFound)?
     .try_into()?;
-  Ok(result)
+
+  Ok(job)
 }
 
-pub async fn add_job(client: &Client, data: AddJobData) -> Result<Job, RepositoryError> {
+/// Finds the jobs among `job_ids` which still exist, in id order.
+pub async fn find_jobs_by_ids<C: GenericClient>(
+  client: &C,
+  job_ids: &[i64],
+) -> Result<Vec<Job>, RepositoryError> {
   let query = format!(
+    "select j.* from {}.jobs j where j.id = any($1::bigint[]) order by j.id asc",
+    (*CONFIG).graphile_worker_schema
+  );
+
+  client
+    .query(&query, &[&job_ids])
+    .await?
+    .into_iter()
+    .map(Job::try_from)
+    .collect()
+}
+
+pub async fn find_job_by_key<C: GenericClient, K: AsRef<str>>(
+  client: &C,
+  job_key: K,
+) -> Result<Job, RepositoryError> {
+  let query = format!(
+    "select j.* from {}.jobs j where j.key = $1::text",
+    (*CONFIG).graphile_worker_schema
+  );
+
+  let job = client
+    .query_opt(&query, &[&job_key.as_ref()])
+    .await?
+    .ok_or(RepositoryError::NotFound)?
+    .try_into()?;
+
+  Ok(job)
+}
+
+fn add_job_query() -> String {
+  format!(
     "select j.* from {}.add_job($1::text, $2::json, $3::text, $4::timestamptz, $5::integer, \
      $6::text, $7::integer, $8::text[], $9::text) j",
     (*CONFIG).graphile_worker_schema
-  );
+  )
+}
+
+fn add_job_params(data: &AddJobData) -> [&(dyn ToSql + Sync); 9] {
+  [
+    &data.task_identifier,
+    &data.payload,
+    &data.queue_name,
+    &data.run_at,
+    &data.max_attempts,
+    &data.job_key,
+    &data.priority,
+    &data.flags,
+    &data.job_key_mode,
+  ]
+}
 
+pub async fn add_job<C: GenericClient>(
+  client: &C,
+  data: AddJobData,
+) -> Result<Job, RepositoryError> {
   let job = client
-    .query_one(
+    .query_one(&add_job_query(), &add_job_params(&data))
+    .await?
+    .try_into()?;
+
+  Ok(job)
+}
+
+/// Adds the jobs in `transaction`, each one behind a savepoint so that a job
+/// rejected by `add_job` is reported with its error message without failing
+/// the others.
+pub async fn add_jobs(
+  transaction: &mut Transaction<'_>,
+  jobs: &[AddJobData],
+) -> Result<Vec<Result<Job, String>>, RepositoryError> {
+  let query = add_job_query();
+  let statement = transaction.prepare(&query).await?;
+
+  let mut results = Vec::with_capacity(jobs.len());
+  for data in jobs {
+    let savepoint = transaction.savepoint("add_job").await?;
+    match savepoint.query_one(&statement, &add_job_params(data)).await {
+      Ok(row) => {
+        savepoint.commit().await?;
+        results.push(Ok(Job::try_from(row)?));
+      }
+      Err(error) => {
+        let message = match error.as_db_error() {
+          Some(db_error) => String::from(db_error.message()),
+          None => return Err(error.into()),
+        };
+        savepoint.rollback().await?;
+        results.push(Err(message));
+      }
+    }
+  }
+
+  Ok(results)
+}
+
+/// Updates an unlocked job, provided it is still at `revision`.
+pub async fn update_job<C: GenericClient>(
+  client: &C,
+  job_id: i64,
+  revision: i32,
+  data: UpdateJobData,
+) -> Result<Job, RepositoryError> {
+  let query = format!(
+    r#"update {}.jobs j set
+        payload = coalesce($3::json, j.payload),
+        queue_name = coalesce($4::text, j.queue_name),
+        task_identifier = coalesce($5::text, j.task_identifier),
+        priority = coalesce($6::integer, j.priority),
+        max_attempts = coalesce($7::integer, j.max_attempts),
+        flags = case when $8::text[] is null then j.flags
+          else (select jsonb_object_agg(flag, true) from unnest($8::text[]) as item(flag)) end,
+        revision = j.revision + 1
+      where j.id = $1::bigint and j.revision = $2::integer and j.locked_at is null
+      returning j.*"#,
+    (*CONFIG).graphile_worker_schema
+  );
+
+  let updated = client
+    .query_opt(
       &query,
       &[
-        &data.task_identifier,
+        &job_id,
+        &revision,
         &data.payload,
         &data.queue_name,
-        &data.run_at,
-        &data.max_attempts,
-        &data.job_key,
+        &data.task_identifier,
         &data.priority,
+        &data.max_attempts,
         &data.flags,
-        &data.job_key_mode,
       ],
     )
-    .await?
-    .try_into()?;
+    .await?;
 
-  Ok(job)
+  match updated {
+    Some(row) => Ok(row.try_into()?),
+    None => {
+      let job = find_job_by_id(client, job_id).await?;
+      if job.locked_at.is_some() {
+        Err(RepositoryError::JobLocked)
+      } else {
+        Err(RepositoryError::RevisionMismatch(job.revision))
+      }
+    }
+  }
 }
 
 #[derive(Serialize)]
@@ -189,15 +891,34 @@ impl TryFrom<Row> for CompleteJobsResult {
   }
 }
 
-pub async fn complete_jobs<I: AsRef<[i64]>>(
-  client: &Client,
+/// Tells `archive_job` why the jobs deleted by the rest of the transaction
+/// were deleted.
+async fn set_deletion_reason<C: GenericClient>(
+  client: &C,
+  reason: DeletionReason,
+) -> Result<(), RepositoryError> {
+  client
+    .execute(
+      "select set_config('graphboard.deletion_reason', $1::text, true)",
+      &[&reason.as_str()],
+    )
+    .await?;
+  Ok(())
+}
+
+/// Completes the jobs, expected to run in a transaction so that archived jobs
+/// record their completion.
+pub async fn complete_jobs<C: GenericClient, I: AsRef<[i64]>>(
+  client: &C,
   job_ids: I,
 ) -> Result<CompleteJobsResult, RepositoryError> {
   let query = format!(
-    "select json_agg(cj)::text completed_jobs from {}.complete_jobs($1::bigint[]) cj",
+    "select coalesce(json_agg(cj), '[]')::text completed_jobs from {}.complete_jobs($1::bigint[]) \
+     cj",
     (*CONFIG).graphile_worker_schema
   );
 
+  set_deletion_reason(client, DeletionReason::Completed).await?;
   let results = client
     .query_one(&query, &[&job_ids.as_ref()])
     .await?
@@ -221,14 +942,14 @@ impl TryFrom<Row> for PermanentlyFailJobsResult {
   }
 }
 
-pub async fn permanently_fail_jobs<I: AsRef<[i64]>, E: AsRef<str>>(
-  client: &Client,
+pub async fn permanently_fail_jobs<C: GenericClient, I: AsRef<[i64]>, E: AsRef<str>>(
+  client: &C,
   job_ids: I,
   error_messages: E,
 ) -> Result<PermanentlyFailJobsResult, RepositoryError> {
   let query = format!(
-    "select json_agg(f)::text permanently_failed_jobs from {}.permanently_fail_jobs($1::bigint[], \
-     $2::text) f",
+    "select coalesce(json_agg(f), '[]')::text permanently_failed_jobs from \
+     {}.permanently_fail_jobs($1::bigint[], $2::text) f",
     (*CONFIG).graphile_worker_schema
   );
 
@@ -240,10 +961,9 @@ pub async fn permanently_fail_jobs<I: AsRef<[i64]>, E: AsRef<str>>(
   Ok(jobs)
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct RescheduleJobsData {
-  pub job_ids: Vec<i64>,
   pub run_at: Option<DateTime<Utc>>,
   pub priority: Option<i32>,
   pub attempts: Option<u32>,
@@ -266,13 +986,14 @@ impl TryFrom<Row> for RescheduleJobsResult {
   }
 }
 
-pub async fn reschedule_jobs(
-  client: &Client,
+pub async fn reschedule_jobs<C: GenericClient, I: AsRef<[i64]>>(
+  client: &C,
+  job_ids: I,
   data: RescheduleJobsData,
 ) -> Result<RescheduleJobsResult, RepositoryError> {
   let query = format!(
-    "select json_agg(r)::text rescheduled_jobs from {}.reschedule_jobs($1::bigint[], \
-     $2::timestamptz, $3::integer, $4::integer, $5::integer) r",
+    "select coalesce(json_agg(r), '[]')::text rescheduled_jobs from \
+     {}.reschedule_jobs($1::bigint[], $2::timestamptz, $3::integer, $4::integer, $5::integer) r",
     (*CONFIG).graphile_worker_schema
   );
 
@@ -280,7 +1001,7 @@ pub async fn reschedule_jobs(
     .query_one(
       &query,
       &[
-        &data.job_ids,
+        &job_ids.as_ref(),
         &data.run_at,
         &data.priority,
         &data.attempts.map(|attempts| attempts as i32),
@@ -293,6 +1014,102 @@ pub async fn reschedule_jobs(
   Ok(result)
 }
 
+/// Makes failed jobs runnable right away with a fresh set of attempts, other
+/// jobs among `job_ids` are left alone. The job row is updated through
+/// `reschedule_jobs` then `max_attempts` is only ever raised, both statements
+/// being expected to run in one transaction.
+pub async fn retry_jobs<C: GenericClient, I: AsRef<[i64]>>(
+  client: &C,
+  job_ids: I,
+  max_attempts: Option<i32>,
+) -> Result<RescheduleJobsResult, RepositoryError> {
+  let reschedule_query = format!(
+    "select r.id from {0}.reschedule_jobs(array(select j.id from {0}.jobs j where j.id = \
+     any($1::bigint[]) and j.locked_at is null and j.attempts >= j.max_attempts), now(), null, 0, \
+     null) r",
+    (*CONFIG).graphile_worker_schema
+  );
+  let clear_error_query = format!(
+    "with r as (update {}.jobs j set last_error = null, max_attempts = greatest(j.max_attempts, \
+     $2::integer) where j.id = any($1::bigint[]) returning *) select coalesce(json_agg(r), \
+     '[]')::text rescheduled_jobs from r",
+    (*CONFIG).graphile_worker_schema
+  );
+
+  let rescheduled_ids = client
+    .query(&reschedule_query, &[&job_ids.as_ref()])
+    .await?
+    .into_iter()
+    .map(|row| row.try_get("id"))
+    .collect::<Result<Vec<i64>, _>>()?;
+  let result = client
+    .query_one(&clear_error_query, &[&rescheduled_ids, &max_attempts])
+    .await?
+    .try_into()?;
+
+  Ok(result)
+}
+
+/// Finds the locked jobs among `job_ids`, or held by `locked_by`, whose lock is
+/// at least `min_lock_age` seconds old.
+pub async fn find_locked_jobs<C: GenericClient>(
+  client: &C,
+  job_ids: Option<&[i64]>,
+  locked_by: Option<&str>,
+  min_lock_age: i64,
+) -> Result<Vec<Job>, RepositoryError> {
+  let query = format!(
+    "select j.* from {}.jobs j where j.locked_at < now() - $3::bigint * interval '1 second' and \
+     (j.id = any($1::bigint[]) or j.locked_by = $2::text) order by j.id asc",
+    (*CONFIG).graphile_worker_schema
+  );
+
+  client
+    .query(&query, &[&job_ids, &locked_by, &min_lock_age])
+    .await?
+    .into_iter()
+    .map(Job::try_from)
+    .collect()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockJobsResult {
+  pub unlocked_jobs: Vec<Job>,
+}
+
+impl TryFrom<Row> for UnlockJobsResult {
+  type Error = RepositoryError;
+
+  fn try_from(row: Row) -> Result<Self, Self::Error> {
+    Ok(UnlockJobsResult {
+      unlocked_jobs: serde_json::from_str(row.try_get("unlocked_jobs")?)?,
+    })
+  }
+}
+
+/// Clears the lock of jobs a worker stopped working on. The lock age is checked
+/// again so that a job picked up again in the meantime is left alone.
+pub async fn unlock_jobs<C: GenericClient, I: AsRef<[i64]>>(
+  client: &C,
+  job_ids: I,
+  min_lock_age: i64,
+) -> Result<UnlockJobsResult, RepositoryError> {
+  let query = format!(
+    "with u as (update {}.jobs set locked_at = null, locked_by = null where id = \
+     any($1::bigint[]) and locked_at < now() - $2::bigint * interval '1 second' returning *) \
+     select coalesce(json_agg(u order by u.id), '[]')::text unlocked_jobs from u",
+    (*CONFIG).graphile_worker_schema
+  );
+
+  let result = client
+    .query_one(&query, &[&job_ids.as_ref(), &min_lock_age])
+    .await?
+    .try_into()?;
+
+  Ok(result)
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RemoveJobsResult {
@@ -313,8 +1130,10 @@ impl TryFrom<Row> for RemoveJobsResult {
   }
 }
 
-pub async fn remove_job<K: AsRef<str>>(
-  client: &Client,
+/// Removes the job by key, expected to run in a transaction so that an archived
+/// job records its removal.
+pub async fn remove_job<C: GenericClient, K: AsRef<str>>(
+  client: &C,
   job_key: K,
 ) -> Result<RemoveJobsResult, RepositoryError> {
   let query = format!(
@@ -323,6 +1142,7 @@ pub async fn remove_job<K: AsRef<str>>(
     (*CONFIG).graphile_worker_schema
   );
 
+  set_deletion_reason(client, DeletionReason::Removed).await?;
   let result = client
     .query_one(&query, &[&job_key.as_ref()])
     .await?
@@ -330,3 +1150,67 @@ pub async fn remove_job<K: AsRef<str>>(
 
   Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn cursor(value: Value) -> RepositoryCursor {
+    RepositoryCursor::decode(base64::encode_config(
+      value.to_string(),
+      base64::URL_SAFE_NO_PAD,
+    ))
+    .unwrap()
+  }
+
+  fn predicate(cursor_value: Value) -> Result<String, RepositoryError> {
+    let keyset = Keyset::new()
+      .push(&JobOrderField::RunAt, true)
+      .push(&JobOrderField::Id, false);
+    keyset.predicate("p", &cursor(cursor_value), false, &mut SqlParams::default())
+  }
+
+  #[test]
+  fn cursor_of_the_same_order_is_bound() {
+    let predicate = predicate(json!({
+      "keys": ["run_at desc", "id asc"],
+      "values": ["2026-10-18T10:00:00.000000Z", "42"]
+    }))
+    .unwrap();
+    assert!(predicate.contains("$1::timestamptz"));
+    assert!(predicate.contains("$2::bigint"));
+  }
+
+  #[test]
+  fn cursor_of_another_order_is_invalid() {
+    for keys in [
+      json!(["priority desc", "id asc"]),
+      json!(["run_at asc", "id asc"]),
+      json!(["run_at desc"]),
+    ] {
+      assert!(matches!(
+        predicate(json!({ "keys": keys, "values": ["2026-10-18T10:00:00Z", "42"] })),
+        Err(RepositoryError::InvalidCursor)
+      ));
+    }
+  }
+
+  #[test]
+  fn tampered_cursor_values_are_invalid() {
+    for values in [
+      json!(["yesterday", "42"]),
+      json!(["2026-10-18T10:00:00Z", "42; drop table jobs"]),
+      json!(["2026-10-18T10:00:00Z"]),
+    ] {
+      assert!(matches!(
+        predicate(json!({ "keys": ["run_at desc", "id asc"], "values": values })),
+        Err(RepositoryError::InvalidCursor)
+      ));
+    }
+    assert!(matches!(
+      RepositoryCursor::decode("not a cursor"),
+      Err(RepositoryError::InvalidCursor)
+    ));
+  }
+}
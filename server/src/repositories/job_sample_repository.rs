@@ -0,0 +1,144 @@
+use crate::{
+  models::JobSamplePoint,
+  repositories::{job_state_sql, like_patterns, RepositoryError},
+  CONFIG,
+};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Client;
+use std::time::Duration;
+
+/// Samples the job counts then applies the retention, unless another
+/// instance holds the sampler lock or sampled less than half an interval ago.
+/// Returns whether a sample was recorded.
+pub async fn record_job_samples(
+  client: &mut Client,
+  interval: Duration,
+  retention_days: i32,
+  history_days: i32,
+) -> Result<bool, RepositoryError> {
+  let schema = &(*CONFIG).graphboard_schema;
+  let transaction = client.transaction().await?;
+
+  let locked: bool = transaction
+    .query_one(
+      "select pg_try_advisory_xact_lock(hashtext('graphboard:sampler'))",
+      &[],
+    )
+    .await?
+    .try_get(0)?;
+  if !locked {
+    return Ok(false);
+  }
+
+  let recently_sampled: bool = transaction
+    .query_one(
+      &format!(
+        "select exists(select 1 from {}.job_samples s where not s.downsampled and s.sampled_at > \
+         now() - $1::float8 * interval '1 second')",
+        schema
+      ),
+      &[&(interval.as_secs_f64() / 2.0)],
+    )
+    .await?
+    .try_get(0)?;
+  if recently_sampled {
+    return Ok(false);
+  }
+
+  transaction
+    .execute(
+      &format!(
+        r#"insert into {schema}.job_samples (sampled_at, task_identifier, state, count)
+          select now(), j.task_identifier, j.state, count(*)
+          from (select j.task_identifier, {state} as state from {worker_schema}.jobs j) j
+          group by j.task_identifier, j.state"#,
+        schema = schema,
+        state = job_state_sql("j"),
+        worker_schema = (*CONFIG).graphile_worker_schema,
+      ),
+      &[],
+    )
+    .await?;
+
+  transaction
+    .execute(
+      &format!(
+        "delete from {}.job_samples s where s.downsampled and s.sampled_at < now() - $1::integer \
+         * interval '1 day'",
+        schema
+      ),
+      &[&history_days],
+    )
+    .await?;
+
+  // Whole hours are averaged at once, the samples where a task had no job
+  // counting as zero
+  transaction
+    .execute(
+      &format!(
+        r#"with old as (
+            delete from {schema}.job_samples s
+            where not s.downsampled and s.sampled_at < date_trunc('hour', now() - $1::integer * interval '1 day')
+            returning *
+          ), instants as (
+            select date_trunc('hour', o.sampled_at) as hour, count(distinct o.sampled_at) as count
+            from old o group by 1
+          )
+          insert into {schema}.job_samples (sampled_at, task_identifier, state, count, downsampled)
+          select i.hour, o.task_identifier, o.state, sum(o.count) / i.count, true
+          from old o join instants i on i.hour = date_trunc('hour', o.sampled_at)
+          group by i.hour, i.count, o.task_identifier, o.state
+          on conflict do nothing"#,
+        schema = schema
+      ),
+      &[&retention_days],
+    )
+    .await?;
+
+  transaction.commit().await?;
+  Ok(true)
+}
+
+/// Averages the samples taken between `from` and `to` by buckets of
+/// `bucket_seconds`, keeping the tasks matching `task_patterns` when set.
+pub async fn find_job_sample_points(
+  client: &Client,
+  from: DateTime<Utc>,
+  to: DateTime<Utc>,
+  bucket_seconds: i64,
+  task_identifier: Option<String>,
+  task_patterns: Option<&[String]>,
+) -> Result<Vec<JobSamplePoint>, RepositoryError> {
+  // Instants are counted over every task so that missing rows average as zero
+  let query = format!(
+    r#"with s as (
+        select to_timestamp(floor(extract(epoch from s.sampled_at) / $3::float8) * $3::float8) as at, s.*
+        from {}.job_samples s
+        where s.sampled_at >= $1::timestamptz and s.sampled_at < $2::timestamptz
+      ), instants as (
+        select s.at, count(distinct s.sampled_at) as count from s group by s.at
+      )
+      select coalesce(json_agg(p order by p.at, p.task_identifier, p.state), '[]')::text from (
+        select s.at, s.task_identifier, s.state, sum(s.count) / i.count as count, max(s.count) as max
+        from s join instants i on i.at = s.at
+        where ($4::text is null or s.task_identifier = $4::text) and
+              ($5::text[] is null or s.task_identifier like any($5::text[]))
+        group by s.at, i.count, s.task_identifier, s.state
+      ) p"#,
+    (*CONFIG).graphboard_schema
+  );
+
+  let row = client
+    .query_one(
+      query.as_str(),
+      &[
+        &from,
+        &to,
+        &(bucket_seconds as f64),
+        &task_identifier,
+        &task_patterns.map(like_patterns),
+      ],
+    )
+    .await?;
+  Ok(serde_json::from_str(row.try_get(0)?)?)
+}
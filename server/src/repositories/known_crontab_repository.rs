@@ -0,0 +1,102 @@
+use crate::{
+  models::{AddJobData, Job, KnownCrontab},
+  repositories::RepositoryError,
+  CONFIG,
+};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Client;
+use tokio_postgres::{GenericClient, Row};
+use tracing::error;
+
+impl TryFrom<Row> for KnownCrontab {
+  type Error = RepositoryError;
+
+  fn try_from(row: Row) -> Result<Self, Self::Error> {
+    let known_crontab_result = {
+      Ok(KnownCrontab {
+        identifier: row.try_get("identifier")?,
+        known_since: row.try_get("known_since")?,
+        last_execution: row.try_get("last_execution")?,
+      })
+    };
+    if let Err(error) = &known_crontab_result {
+      error!(
+        error = format!("{:?}", error).as_str(),
+        "Error while deserializing row to KnownCrontab"
+      );
+    }
+    known_crontab_result
+  }
+}
+
+pub async fn find_known_crontabs(client: &Client) -> Result<Vec<KnownCrontab>, RepositoryError> {
+  let query = format!(
+    "select k.* from {}.known_crontabs k order by k.identifier asc",
+    (*CONFIG).graphile_worker_schema
+  );
+
+  client
+    .query(&query, &[])
+    .await?
+    .into_iter()
+    .map(KnownCrontab::try_from)
+    .collect()
+}
+
+pub async fn find_known_crontab<C: GenericClient, I: AsRef<str>>(
+  client: &C,
+  identifier: I,
+) -> Result<KnownCrontab, RepositoryError> {
+  let query = format!(
+    "select k.* from {}.known_crontabs k where k.identifier = $1::text",
+    (*CONFIG).graphile_worker_schema
+  );
+
+  let known_crontab = client
+    .query_opt(&query, &[&identifier.as_ref()])
+    .await?
+    .ok_or(RepositoryError::NotFound)?
+    .try_into()?;
+
+  Ok(known_crontab)
+}
+
+/// Schedules the `ts` execution of a crontab the way Graphile Worker does: the
+/// job is only added when `last_execution` can be moved forward to `ts`, so a
+/// slot is never scheduled twice. Returns `None` when it already was.
+pub async fn add_known_crontab_job<C: GenericClient, I: AsRef<str>>(
+  client: &C,
+  identifier: I,
+  ts: DateTime<Utc>,
+  data: AddJobData,
+) -> Result<Option<Job>, RepositoryError> {
+  let schema = &(*CONFIG).graphile_worker_schema;
+  let query = format!(
+    "with locks as (update {schema}.known_crontabs k set last_execution = $2::timestamptz where \
+     k.identifier = $1::text and (k.last_execution is null or k.last_execution < $2::timestamptz) \
+     returning k.identifier) select row_to_json({schema}.add_job($3::text, $4::json, $5::text, \
+     $2::timestamptz, $6::integer, null, $7::integer, $8::text[]))::text as job from locks",
+    schema = schema
+  );
+
+  let row = client
+    .query_opt(
+      &query,
+      &[
+        &identifier.as_ref(),
+        &ts,
+        &data.task_identifier,
+        &data.payload,
+        &data.queue_name,
+        &data.max_attempts,
+        &data.priority,
+        &data.flags,
+      ],
+    )
+    .await?;
+
+  match row {
+    Some(row) => Ok(Some(serde_json::from_str(row.try_get("job")?)?)),
+    None => Ok(None),
+  }
+}
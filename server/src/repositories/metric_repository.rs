@@ -0,0 +1,80 @@
+use crate::{
+  models::JobQueue,
+  repositories::{job_state_sql, RepositoryError, LOCK_EXPIRY},
+  CONFIG,
+};
+use deadpool_postgres::Client;
+
+pub struct JobMetrics {
+  /// Job count by task identifier and state
+  pub jobs: Vec<(String, String, i64)>,
+  /// Count by task identifier of unlocked jobs which should have run already
+  pub past_due_jobs: Vec<(String, i64)>,
+  pub stale_locked_jobs: i64,
+  pub job_queues: Vec<JobQueue>,
+}
+
+pub async fn find_job_metrics(client: &Client) -> Result<JobMetrics, RepositoryError> {
+  let schema = &(*CONFIG).graphile_worker_schema;
+
+  let jobs = client
+    .query(
+      &format!(
+        "select j.task_identifier, {state} as state, count(*) as count from {schema}.jobs j group \
+         by 1, 2 order by 1, 2",
+        state = job_state_sql("j"),
+        schema = schema
+      ),
+      &[],
+    )
+    .await?
+    .into_iter()
+    .map(|row| Ok((row.try_get(0)?, row.try_get(1)?, row.try_get(2)?)))
+    .collect::<Result<_, RepositoryError>>()?;
+
+  // Permanently failed jobs never run again, so they are not counted as past due
+  let past_due_jobs = client
+    .query(
+      &format!(
+        "select j.task_identifier, count(*) as count from {}.jobs j where j.run_at < now() and \
+         j.locked_at is null and j.attempts < j.max_attempts group by 1 order by 1",
+        schema
+      ),
+      &[],
+    )
+    .await?
+    .into_iter()
+    .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+    .collect::<Result<_, RepositoryError>>()?;
+
+  let stale_locked_jobs = client
+    .query_one(
+      &format!(
+        "select count(*) from {}.jobs j where j.locked_at < now() - {}",
+        schema, LOCK_EXPIRY
+      ),
+      &[],
+    )
+    .await?
+    .try_get(0)?;
+
+  let job_queues = client
+    .query(
+      &format!(
+        "select q.* from {}.job_queues q order by q.queue_name",
+        schema
+      ),
+      &[],
+    )
+    .await?
+    .into_iter()
+    .map(JobQueue::try_from)
+    .collect::<Result<_, RepositoryError>>()?;
+
+  Ok(JobMetrics {
+    jobs,
+    past_due_jobs,
+    stale_locked_jobs,
+    job_queues,
+  })
+}
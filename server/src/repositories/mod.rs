@@ -1,20 +1,45 @@
 extern crate derive_more;
 
 use actix_web::HttpResponse;
+use chrono::{DateTime, Utc};
 use deadpool_postgres::PoolError;
 use derive_more::{Display, From};
-use serde::Deserialize;
-use serde_json::{json, Error as SerdeError};
-use tokio_postgres::error::Error as PGError;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{json, Error as SerdeError, Value};
+use std::str::FromStr;
+use tokio_postgres::{error::Error as PGError, types::ToSql};
 
+mod api_token_repository;
+mod archived_job_repository;
+mod audit_entry_repository;
+mod job_queue_repository;
 mod job_repository;
+mod job_sample_repository;
+mod known_crontab_repository;
+mod metric_repository;
+mod paused_queue_repository;
+mod stats_repository;
+mod worker_repository;
 
 use crate::errors::HttpError;
+pub use api_token_repository::*;
+pub use archived_job_repository::*;
+pub use audit_entry_repository::*;
+pub use job_queue_repository::*;
 pub use job_repository::*;
+pub use job_sample_repository::*;
+pub use known_crontab_repository::*;
+pub use metric_repository::*;
+pub use paused_queue_repository::*;
+pub use stats_repository::*;
+pub use worker_repository::*;
 
 #[derive(Display, From, Debug)]
 pub enum RepositoryError {
   NotFound,
+  InvalidCursor,
+  JobLocked,
+  RevisionMismatch(i32),
   PGError(PGError),
   MappingError(SerdeError),
   PoolError(PoolError),
@@ -26,6 +51,11 @@ impl From<RepositoryError> for HttpError {
   fn from(error: RepositoryError) -> Self {
     match error {
       RepositoryError::NotFound => HttpError::not_found("NTFND", None),
+      RepositoryError::InvalidCursor => HttpError::bad_request("IVCRS", None),
+      RepositoryError::JobLocked => HttpError::conflict("JBLCK", None),
+      RepositoryError::RevisionMismatch(revision) => {
+        HttpError::conflict("RVMSM", Some(json!({ "revision": revision })))
+      }
       RepositoryError::PGError(ref error) => {
         #[cfg(debug_assertions)]
         return HttpError::internal_server_error(
@@ -70,6 +100,80 @@ pub enum RepositoryOrder<Field> {
   Desc(Field),
 }
 
+impl<Field> RepositoryOrder<Field> {
+  pub fn field(&self) -> &Field {
+    match self {
+      RepositoryOrder::Asc(field) | RepositoryOrder::Desc(field) => field,
+    }
+  }
+
+  pub fn is_descending(&self) -> bool {
+    matches!(self, RepositoryOrder::Desc(_))
+  }
+}
+
+/// Sort keys applied in order. Deserializes either from a single order or from
+/// a list of orders, which query strings encode as `order[0][field]=...`.
+#[derive(Clone, Debug)]
+pub struct RepositoryOrderList<Field>(Vec<RepositoryOrder<Field>>);
+
+impl<Field> RepositoryOrderList<Field> {
+  pub fn orders(&self) -> &[RepositoryOrder<Field>] {
+    &self.0
+  }
+}
+
+impl<'de, Field: DeserializeOwned> Deserialize<'de> for RepositoryOrderList<Field> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    use serde::de::Error;
+
+    let orders = match Value::deserialize(deserializer)? {
+      Value::Array(orders) => orders,
+      Value::Object(order) if order.contains_key("field") || order.contains_key("direction") => {
+        vec![Value::Object(order)]
+      }
+      Value::Object(orders) => {
+        let mut indexed = orders
+          .into_iter()
+          .map(|(index, order)| {
+            index
+              .parse::<usize>()
+              .map(|index| (index, order))
+              .map_err(|_| D::Error::custom(format!("invalid order index '{}'", index)))
+          })
+          .collect::<Result<Vec<_>, _>>()?;
+        indexed.sort_by_key(|(index, _)| *index);
+        indexed.into_iter().map(|(_, order)| order).collect()
+      }
+      _ => return Err(D::Error::custom("expected an order or a list of orders")),
+    };
+
+    orders
+      .into_iter()
+      .map(serde_json::from_value)
+      .collect::<Result<Vec<_>, _>>()
+      .map(RepositoryOrderList)
+      .map_err(D::Error::custom)
+  }
+}
+
+impl<Field: Default> Default for RepositoryOrderList<Field> {
+  fn default() -> Self {
+    RepositoryOrderList(vec![RepositoryOrder::default()])
+  }
+}
+
+impl<Field: ToSqlIdent> Order for RepositoryOrderList<Field> {
+  fn order(&self) -> String {
+    self
+      .0
+      .iter()
+      .map(|order| order.order())
+      .collect::<Vec<_>>()
+      .join(", ")
+  }
+}
+
 pub trait ToSqlIdent {
   fn sql_ident(&self) -> String;
 }
@@ -149,3 +253,235 @@ impl Pagination for Option<RepositoryPagination> {
     0
   }
 }
+
+/// Positional parameters of a dynamically built statement.
+#[derive(Default)]
+pub struct SqlParams(Vec<Box<dyn ToSql + Sync + Send>>);
+
+impl SqlParams {
+  /// Binds `value` and returns its placeholder.
+  pub fn push<T: ToSql + Sync + Send + 'static>(&mut self, value: T) -> String {
+    self.0.push(Box::new(value));
+    format!("${}", self.0.len())
+  }
+
+  pub fn as_refs(&self) -> Vec<&(dyn ToSql + Sync)> {
+    self
+      .0
+      .iter()
+      .map(|param| param.as_ref() as &(dyn ToSql + Sync))
+      .collect()
+  }
+}
+
+/// Expiry applied by Graphile Worker's `get_job` to job and queue locks.
+pub const LOCK_EXPIRY: &str = "interval '4 hours'";
+
+/// Translates `*` and `?` scope patterns to `like` patterns, escaping the
+/// characters `like` would otherwise interpret.
+pub fn like_patterns(patterns: &[String]) -> Vec<String> {
+  patterns
+    .iter()
+    .map(|pattern| {
+      let mut like = String::with_capacity(pattern.len());
+      for character in pattern.chars() {
+        match character {
+          '*' => like.push('%'),
+          '?' => like.push('_'),
+          '\\' | '%' | '_' => {
+            like.push('\\');
+            like.push(character);
+          }
+          _ => like.push(character),
+        }
+      }
+      like
+    })
+    .collect()
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum CountMode {
+  Exact,
+  Estimated,
+  #[serde(rename = "none")]
+  Omitted,
+}
+
+impl Default for CountMode {
+  fn default() -> Self {
+    CountMode::Exact
+  }
+}
+
+/// An order field usable for keyset pagination over rows of type `T`.
+pub trait KeysetField<T>: ToSqlIdent {
+  fn sql_type(&self) -> &'static str;
+  fn cursor_value(&self, item: &T) -> Option<String>;
+}
+
+/// Opaque position of a row within an ordering: the sort keys, so that the
+/// cursor is only used with the ordering it comes from, and the value of each
+/// of them for that row, encoded as url-safe base64 JSON.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RepositoryCursor {
+  keys: Vec<String>,
+  values: Vec<Option<String>>,
+}
+
+impl RepositoryCursor {
+  pub fn encode(&self) -> String {
+    let json = serde_json::to_vec(self).unwrap_or_default();
+    base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+  }
+
+  pub fn decode<C: AsRef<str>>(raw: C) -> Result<Self, RepositoryError> {
+    let json = base64::decode_config(raw.as_ref(), base64::URL_SAFE_NO_PAD)
+      .map_err(|_| RepositoryError::InvalidCursor)?;
+    serde_json::from_slice(&json).map_err(|_| RepositoryError::InvalidCursor)
+  }
+}
+
+fn parse_cursor_value<T: FromStr>(value: &Option<String>) -> Result<Option<T>, RepositoryError> {
+  value
+    .as_deref()
+    .map(|value| value.parse().map_err(|_| RepositoryError::InvalidCursor))
+    .transpose()
+}
+
+/// Binds a cursor value with the type of its sort key, so that a tampered value
+/// is an invalid cursor rather than a failing cast.
+fn push_cursor_value(
+  sql_params: &mut SqlParams,
+  sql_type: &str,
+  value: &Option<String>,
+) -> Result<String, RepositoryError> {
+  Ok(match sql_type {
+    "bigint" => sql_params.push(parse_cursor_value::<i64>(value)?),
+    "integer" => sql_params.push(parse_cursor_value::<i32>(value)?),
+    "timestamptz" => sql_params.push(parse_cursor_value::<DateTime<Utc>>(value)?),
+    _ => sql_params.push(value.clone()),
+  })
+}
+
+/// Sort keys of a keyset paginated query. Null values are compared the way
+/// Postgres sorts them by default, i.e. as larger than any other value.
+pub struct Keyset<'a, T> {
+  keys: Vec<(&'a dyn KeysetField<T>, bool)>,
+}
+
+impl<'a, T> Keyset<'a, T> {
+  pub fn new() -> Self {
+    Keyset { keys: Vec::new() }
+  }
+
+  pub fn push(mut self, field: &'a dyn KeysetField<T>, descending: bool) -> Self {
+    self.keys.push((field, descending));
+    self
+  }
+
+  fn key_names(&self) -> Vec<String> {
+    self
+      .keys
+      .iter()
+      .map(|(field, descending)| {
+        format!(
+          "{} {}",
+          field.sql_ident(),
+          if *descending { "desc" } else { "asc" }
+        )
+      })
+      .collect()
+  }
+
+  pub fn order(&self, backward: bool) -> String {
+    self
+      .keys
+      .iter()
+      .map(|(field, descending)| {
+        let direction = if *descending != backward {
+          "desc"
+        } else {
+          "asc"
+        };
+        format!("{} {}", field.sql_ident(), direction)
+      })
+      .collect::<Vec<_>>()
+      .join(", ")
+  }
+
+  /// Builds the predicate selecting rows after (or before, when `backward`)
+  /// the cursor and binds its values. Cursors of another ordering, or whose
+  /// values do not parse, are invalid.
+  pub fn predicate(
+    &self,
+    alias: &str,
+    cursor: &RepositoryCursor,
+    backward: bool,
+    sql_params: &mut SqlParams,
+  ) -> Result<String, RepositoryError> {
+    if cursor.keys != self.key_names() || cursor.values.len() != self.keys.len() {
+      return Err(RepositoryError::InvalidCursor);
+    }
+    let values = self
+      .keys
+      .iter()
+      .zip(&cursor.values)
+      .map(|((field, _), value)| {
+        Ok(format!(
+          "{}::{}",
+          push_cursor_value(sql_params, field.sql_type(), value)?,
+          field.sql_type()
+        ))
+      })
+      .collect::<Result<Vec<String>, RepositoryError>>()?;
+    let column = |field: &dyn KeysetField<T>| format!("{}.{}", alias, field.sql_ident());
+
+    let alternatives: Vec<String> = self
+      .keys
+      .iter()
+      .enumerate()
+      .map(|(index, (field, descending))| {
+        let mut conditions: Vec<String> = self.keys[..index]
+          .iter()
+          .enumerate()
+          .map(|(previous, (previous_field, _))| {
+            format!(
+              "{} is not distinct from {}",
+              column(*previous_field),
+              values[previous]
+            )
+          })
+          .collect();
+        let (column, value) = (column(*field), &values[index]);
+        conditions.push(if *descending != backward {
+          format!(
+            "(case when {c} is null then false when {v} is null then true else {c} < {v} end)",
+            c = column,
+            v = value
+          )
+        } else {
+          format!(
+            "(case when {v} is null then false when {c} is null then true else {c} > {v} end)",
+            c = column,
+            v = value
+          )
+        });
+        format!("({})", conditions.join(" and "))
+      })
+      .collect();
+    Ok(format!("({})", alternatives.join(" or ")))
+  }
+
+  pub fn cursor(&self, item: &T) -> RepositoryCursor {
+    RepositoryCursor {
+      keys: self.key_names(),
+      values: self
+        .keys
+        .iter()
+        .map(|(field, _)| field.cursor_value(item))
+        .collect(),
+    }
+  }
+}
@@ -0,0 +1,120 @@
+use crate::{
+  models::{PausedQueue, PAUSED_LOCK_PREFIX},
+  repositories::RepositoryError,
+  CONFIG,
+};
+use tokio_postgres::{GenericClient, Row};
+use tracing::error;
+
+impl TryFrom<Row> for PausedQueue {
+  type Error = RepositoryError;
+
+  fn try_from(row: Row) -> Result<Self, Self::Error> {
+    let paused_queue_result = {
+      Ok(PausedQueue {
+        queue_name: row.try_get("queue_name")?,
+        paused_by: row.try_get("paused_by")?,
+        paused_at: row.try_get("paused_at")?,
+      })
+    };
+    if let Err(error) = &paused_queue_result {
+      error!(
+        error = format!("{:?}", error).as_str(),
+        "Error while deserializing row to PausedQueue"
+      );
+    }
+    paused_queue_result
+  }
+}
+
+/// Holds the paused job queues (or only `queue_name`) with the pause marker.
+/// The lock is taken over from a worker running one of their jobs, so that
+/// the pause applies once the job is done, and refreshed well before the
+/// `get_job` expiry.
+pub async fn lock_paused_job_queues<C: GenericClient>(
+  client: &C,
+  queue_name: Option<&str>,
+) -> Result<u64, RepositoryError> {
+  let query = format!(
+    "update {}.job_queues q set locked_by = $2::text || p.paused_by, locked_at = now() from \
+     {}.paused_queues p where q.queue_name = p.queue_name and ($1::text is null or q.queue_name = \
+     $1::text) and (q.locked_by is distinct from $2::text || p.paused_by or q.locked_at < now() - \
+     interval '1 hour')",
+    (*CONFIG).graphile_worker_schema,
+    (*CONFIG).graphboard_schema
+  );
+
+  Ok(
+    client
+      .execute(&query, &[&queue_name, &PAUSED_LOCK_PREFIX])
+      .await?,
+  )
+}
+
+/// Pauses the job queue, keeping the original pause when already paused.
+/// Expected to run in a transaction, as the following resume.
+pub async fn pause_job_queue<C: GenericClient>(
+  client: &C,
+  queue_name: &str,
+  paused_by: &str,
+) -> Result<PausedQueue, RepositoryError> {
+  let schema = &(*CONFIG).graphboard_schema;
+
+  client
+    .execute(
+      &format!(
+        "insert into {}.paused_queues (queue_name, paused_by) values ($1::text, $2::text) on \
+         conflict (queue_name) do nothing",
+        schema
+      ),
+      &[&queue_name, &paused_by],
+    )
+    .await?;
+  let paused_queue = client
+    .query_one(
+      &format!(
+        "select p.* from {}.paused_queues p where p.queue_name = $1::text",
+        schema
+      ),
+      &[&queue_name],
+    )
+    .await?
+    .try_into()?;
+  lock_paused_job_queues(client, Some(queue_name)).await?;
+
+  Ok(paused_queue)
+}
+
+/// Resumes the job queue. The lock goes back to the worker still running one of
+/// its jobs if any, Graphile Worker releasing it once the job is done, so that
+/// no other job of the queue starts meanwhile.
+pub async fn resume_job_queue<C: GenericClient>(
+  client: &C,
+  queue_name: &str,
+) -> Result<PausedQueue, RepositoryError> {
+  let paused_queue = client
+    .query_opt(
+      &format!(
+        "delete from {}.paused_queues p where p.queue_name = $1::text returning p.*",
+        (*CONFIG).graphboard_schema
+      ),
+      &[&queue_name],
+    )
+    .await?
+    .ok_or(RepositoryError::NotFound)?
+    .try_into()?;
+  client
+    .execute(
+      &format!(
+        "update {0}.job_queues q set (locked_by, locked_at) = (select j.locked_by, j.locked_at \
+         from {0}.jobs j where j.queue_name = q.queue_name and j.locked_at is not null order by \
+         j.locked_at desc limit 1) where q.queue_name = $1::text and q.locked_by like $2::text || \
+         '%'",
+        (*CONFIG).graphile_worker_schema
+      ),
+      &[&queue_name, &PAUSED_LOCK_PREFIX],
+    )
+    .await?;
+
+  Ok(paused_queue)
+}
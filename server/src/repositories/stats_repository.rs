@@ -0,0 +1,108 @@
+use crate::{
+  models::{JobScope, JobState},
+  repositories::{job_state_sql, FindJobsFilters, JobStateCounts, RepositoryError, SqlParams},
+  CONFIG,
+};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Client;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct ErrorCount {
+  pub message: String,
+  pub count: i64,
+}
+
+/// Aggregates over the jobs sharing a task identifier or a queue name.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct JobGroupStats {
+  /// `None` groups the jobs without queue
+  pub name: Option<String>,
+  pub count: i64,
+  pub state_counts: JobStateCounts,
+  /// Earliest `run_at` among unlocked jobs with attempts left
+  pub oldest_pending_run_at: Option<DateTime<Utc>>,
+  pub average_attempts: f64,
+  /// Highest attempts made by a job of the group, not its configured limit
+  pub max_attempts: i32,
+  /// Most frequent errors, compared on their first line only
+  pub top_errors: Vec<ErrorCount>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStats {
+  pub tasks: Vec<JobGroupStats>,
+  pub queues: Vec<JobGroupStats>,
+}
+
+async fn find_job_group_stats(
+  client: &Client,
+  group_column: &str,
+  top_errors: i64,
+  scope: &JobScope,
+) -> Result<Vec<JobGroupStats>, RepositoryError> {
+  let mut sql_params = SqlParams::default();
+  let top_errors = sql_params.push(top_errors);
+  let query = format!(
+    r#"with j as (
+        select j.{group_column} as name, j.*, {state} as state from {schema}.jobs j
+        where {clause}
+      ), groups as (
+        select j.name, count(*) as count,
+          json_build_object({state_counts}) as state_counts,
+          min(j.run_at) filter (where j.locked_at is null and j.attempts < j.max_attempts)
+            as oldest_pending_run_at,
+          coalesce(avg(j.attempts), 0)::float8 as average_attempts,
+          coalesce(max(j.attempts), 0) as max_attempts
+        from j group by j.name
+      ), errors as (
+        select e.*, row_number() over (partition by e.name order by e.count desc, e.message) as rank
+        from (
+          select j.name, split_part(j.last_error, E'\n', 1) as message, count(*) as count
+          from j where j.last_error is not null group by 1, 2
+        ) e
+      )
+      select coalesce(json_agg(s order by s.name nulls first), '[]')::text from (
+        select g.*, coalesce((
+          select json_agg(json_build_object('message', e.message, 'count', e.count) order by e.rank)
+          from errors e where e.name is not distinct from g.name and e.rank <= {top_errors}
+        ), '[]') as top_errors
+        from groups g
+      ) s"#,
+    group_column = group_column,
+    top_errors = top_errors,
+    clause = FindJobsFilters::default()
+      .scoped(scope)
+      .where_clause("j", &mut sql_params),
+    state = job_state_sql("j"),
+    schema = (*CONFIG).graphile_worker_schema,
+    state_counts = JobState::ALL
+      .iter()
+      .map(|state| format!(
+        "'{0}', count(*) filter (where j.state = '{0}')",
+        state.as_str()
+      ))
+      .collect::<Vec<_>>()
+      .join(", "),
+  );
+
+  let row = client
+    .query_one(query.as_str(), &sql_params.as_refs())
+    .await?;
+  Ok(serde_json::from_str(row.try_get(0)?)?)
+}
+
+/// Aggregates the jobs `scope` allows.
+pub async fn find_job_stats(
+  client: &Client,
+  top_errors: i64,
+  scope: &JobScope,
+) -> Result<JobStats, RepositoryError> {
+  Ok(JobStats {
+    tasks: find_job_group_stats(client, "task_identifier", top_errors, scope).await?,
+    queues: find_job_group_stats(client, "queue_name", top_errors, scope).await?,
+  })
+}
@@ -0,0 +1,128 @@
+use crate::{
+  models::{JobScope, Worker, WorkerJobLock, WorkerQueueLock, PAUSED_LOCK_PREFIX},
+  repositories::{RepositoryError, LOCK_EXPIRY},
+  CONFIG,
+};
+use deadpool_postgres::Client;
+use std::collections::BTreeMap;
+use tokio_postgres::Row;
+use tracing::error;
+
+impl TryFrom<Row> for WorkerJobLock {
+  type Error = RepositoryError;
+
+  fn try_from(row: Row) -> Result<Self, Self::Error> {
+    let lock_result = {
+      Ok(WorkerJobLock {
+        id: row.try_get("id")?,
+        task_identifier: row.try_get("task_identifier")?,
+        queue_name: row.try_get("queue_name")?,
+        locked_at: row.try_get("locked_at")?,
+        lock_age_seconds: row.try_get("lock_age_seconds")?,
+        expired: row.try_get("expired")?,
+      })
+    };
+    if let Err(error) = &lock_result {
+      error!(
+        error = format!("{:?}", error).as_str(),
+        "Error while deserializing row to WorkerJobLock"
+      );
+    }
+    lock_result
+  }
+}
+
+impl TryFrom<Row> for WorkerQueueLock {
+  type Error = RepositoryError;
+
+  fn try_from(row: Row) -> Result<Self, Self::Error> {
+    let lock_result = {
+      Ok(WorkerQueueLock {
+        queue_name: row.try_get("queue_name")?,
+        locked_at: row.try_get("locked_at")?,
+        lock_age_seconds: row.try_get("lock_age_seconds")?,
+        expired: row.try_get("expired")?,
+      })
+    };
+    if let Err(error) = &lock_result {
+      error!(
+        error = format!("{:?}", error).as_str(),
+        "Error while deserializing row to WorkerQueueLock"
+      );
+    }
+    lock_result
+  }
+}
+
+/// Groups the locked jobs and queues `scope` allows by `locked_by`, oldest
+/// locks first. Queues held by a graphboard pause are not worker locks, workers
+/// left without any lock are left out.
+pub async fn find_workers(
+  client: &Client,
+  scope: &JobScope,
+) -> Result<Vec<Worker>, RepositoryError> {
+  let schema = &(*CONFIG).graphile_worker_schema;
+  let lock_columns = format!(
+    "x.locked_by, x.locked_at, extract(epoch from now() - x.locked_at)::float8 as \
+     lock_age_seconds, x.locked_at < now() - {} as expired",
+    LOCK_EXPIRY
+  );
+
+  let job_rows = client
+    .query(
+      &format!(
+        "select x.id, x.task_identifier, x.queue_name, {} from {}.jobs x where x.locked_by is not \
+         null and x.locked_at is not null order by x.locked_at, x.id",
+        lock_columns, schema
+      ),
+      &[],
+    )
+    .await?;
+  let queue_rows = client
+    .query(
+      &format!(
+        "select x.queue_name, {} from {}.job_queues x where x.locked_by is not null and \
+         x.locked_at is not null and x.locked_by not like '{}%' order by x.locked_at, x.queue_name",
+        lock_columns, schema, PAUSED_LOCK_PREFIX
+      ),
+      &[],
+    )
+    .await?;
+
+  let mut workers: BTreeMap<String, (Vec<WorkerJobLock>, Vec<WorkerQueueLock>)> = BTreeMap::new();
+  for row in job_rows {
+    let worker_id: String = row.try_get("locked_by")?;
+    let lock = WorkerJobLock::try_from(row)?;
+    if scope.allows(lock.queue_name.as_deref(), &lock.task_identifier) {
+      workers.entry(worker_id).or_default().0.push(lock);
+    }
+  }
+  for row in queue_rows {
+    let worker_id: String = row.try_get("locked_by")?;
+    let lock = WorkerQueueLock::try_from(row)?;
+    if scope.allows_queue(&lock.queue_name) {
+      workers.entry(worker_id).or_default().1.push(lock);
+    }
+  }
+
+  Ok(
+    workers
+      .into_iter()
+      .filter_map(|(worker_id, (jobs, queues))| {
+        let oldest_locked_at = jobs
+          .iter()
+          .map(|lock| lock.locked_at)
+          .chain(queues.iter().map(|lock| lock.locked_at))
+          .min()?;
+        Some(Worker {
+          worker_id,
+          oldest_locked_at,
+          expired_lock_count: jobs.iter().filter(|lock| lock.expired).count()
+            + queues.iter().filter(|lock| lock.expired).count(),
+          jobs,
+          queues,
+        })
+      })
+      .collect(),
+  )
+}
@@ -0,0 +1,36 @@
+use crate::{config::CONFIG, repositories::record_job_samples};
+use actix_web::rt;
+use deadpool_postgres::Pool;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, error};
+
+/// Periodically records the job counts by task identifier and state. Every
+/// instance runs the sampler, the repository making sure only one samples.
+pub fn start_sampler(pool: Pool) {
+  let sampler = &(*CONFIG).sampler;
+  if !sampler.enabled {
+    return;
+  }
+  let period = Duration::from_secs(sampler.interval.max(1));
+
+  rt::spawn(async move {
+    let mut ticks = interval(period);
+    loop {
+      ticks.tick().await;
+      let result = match pool.get().await {
+        Ok(mut client) => {
+          record_job_samples(&mut client, period, sampler.retention, sampler.history).await
+        }
+        Err(error) => Err(error.into()),
+      };
+      match result {
+        Ok(recorded) => debug!(recorded, "Job samples tick"),
+        Err(error) => error!(
+          error = format!("{:?}", error).as_str(),
+          "Error while recording job samples"
+        ),
+      }
+    }
+  });
+}
@@ -0,0 +1,124 @@
+use crate::{
+  errors::HttpError,
+  middlewares::{hash_token, Principal, RequireAdmin},
+  models::{ApiToken, JobScope, Role},
+  repositories::{create_api_token, find_api_tokens, revoke_api_token},
+  services::audit_service::audit,
+};
+use actix_web::{
+  get, post,
+  web::{scope, Data, HttpRequest, Json},
+  HttpResponse, Scope,
+};
+use deadpool_postgres::Pool;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+pub fn api_tokens_service() -> Scope {
+  scope("/api-tokens")
+    .service(find_api_tokens_route)
+    .service(create_api_token_route)
+    .service(revoke_api_token_route)
+}
+
+#[get("", wrap = "RequireAdmin")]
+pub async fn find_api_tokens_route(pool: Data<Pool>) -> Result<HttpResponse, HttpError> {
+  let api_tokens = find_api_tokens(&pool.get().await?).await?;
+  Ok(HttpResponse::Ok().json(api_tokens))
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiTokenBody {
+  pub name: String,
+  #[serde(default)]
+  pub role: Role,
+  #[serde(flatten)]
+  pub scope: JobScope,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedApiToken {
+  #[serde(flatten)]
+  pub api_token: ApiToken,
+  /// Only ever returned here, the database keeps its hash
+  pub token: String,
+}
+
+#[post("", wrap = "RequireAdmin")]
+pub async fn create_api_token_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+  body: Json<CreateApiTokenBody>,
+) -> Result<HttpResponse, HttpError> {
+  let name = body.name.trim();
+  if name.is_empty() || name.len() > 128 {
+    return Err(HttpError::bad_request(
+      "IVTNM",
+      Some(json!({ "message": "Token name must be between 1 and 128 characters" })),
+    ));
+  }
+
+  let mut secret = [0u8; 32];
+  rand::thread_rng().fill_bytes(&mut secret);
+  let token = format!(
+    "gb_{}",
+    base64::encode_config(secret, base64::URL_SAFE_NO_PAD)
+  );
+
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+  let api_token = create_api_token(
+    &*transaction,
+    name,
+    body.role,
+    &body.scope,
+    &hash_token(&token),
+  )
+  .await?;
+  // The token itself is left out of the audit log
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &body.0,
+    vec![],
+    None,
+    &api_token,
+  )
+  .await?;
+  transaction.commit().await?;
+  Ok(HttpResponse::Ok().json(CreatedApiToken { api_token, token }))
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RevokeApiTokenBody {
+  pub id: i32,
+}
+
+#[post("/revoke", wrap = "RequireAdmin")]
+pub async fn revoke_api_token_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+  body: Json<RevokeApiTokenBody>,
+) -> Result<HttpResponse, HttpError> {
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+  let api_token = revoke_api_token(&*transaction, body.id).await?;
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &body.0,
+    vec![],
+    None,
+    &api_token,
+  )
+  .await?;
+  transaction.commit().await?;
+  Ok(HttpResponse::Ok().json(api_token))
+}
@@ -0,0 +1,33 @@
+use crate::{
+  errors::HttpError,
+  middlewares::{Principal, RequireViewer},
+  repositories::{find_archived_jobs, FindJobsParams},
+};
+use actix_web::{
+  get,
+  web::{scope, Data, HttpRequest},
+  HttpResponse, Scope,
+};
+use deadpool_postgres::Pool;
+use serde_json::json;
+
+pub fn archived_jobs_service() -> Scope {
+  scope("/archived-jobs").service(find_archived_jobs_route)
+}
+
+#[get("", wrap = "RequireViewer")]
+pub async fn find_archived_jobs_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+) -> Result<HttpResponse, HttpError> {
+  let params = serde_qs::from_str::<FindJobsParams>(req.query_string())?.scoped(&principal.scope);
+  if params.filters().has_state() {
+    return Err(HttpError::bad_request(
+      "IVFLT",
+      Some(json!({ "message": "Archived jobs have a deletion reason rather than a state" })),
+    ));
+  }
+  let archived_jobs = find_archived_jobs(&pool.get().await?, params).await?;
+  Ok(HttpResponse::Ok().json(archived_jobs))
+}
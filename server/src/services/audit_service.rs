@@ -0,0 +1,64 @@
+use crate::{
+  errors::HttpError,
+  middlewares::{Principal, RequireAdmin},
+  models::AddAuditEntryData,
+  repositories::{add_audit_entry, find_audit_entries},
+};
+use actix_web::{
+  get,
+  web::{scope, Data, HttpRequest},
+  HttpResponse, Scope,
+};
+use deadpool_postgres::{Pool, Transaction};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+pub fn audit_service() -> Scope {
+  scope("/audit").service(find_audit_entries_route)
+}
+
+#[get("", wrap = "RequireAdmin")]
+pub async fn find_audit_entries_route(
+  req: HttpRequest,
+  pool: Data<Pool>,
+) -> Result<HttpResponse, HttpError> {
+  let params = serde_qs::from_str(req.query_string())?;
+  let audit_entries = find_audit_entries(&pool.get().await?, params).await?;
+  Ok(HttpResponse::Ok().json(audit_entries))
+}
+
+/// Records a mutating call in the transaction making it, so that the entry is
+/// kept if and only if the call is. `before` holds the affected rows as they
+/// were and `after` the response sent back.
+pub async fn audit(
+  transaction: &Transaction<'_>,
+  req: &HttpRequest,
+  principal: &Principal,
+  body: &impl Serialize,
+  job_ids: Vec<i64>,
+  before: Option<Value>,
+  after: &impl Serialize,
+) -> Result<(), HttpError> {
+  let route = format!(
+    "{} {}",
+    req.method(),
+    req
+      .match_pattern()
+      .unwrap_or_else(|| String::from(req.path()))
+  );
+
+  add_audit_entry(
+    &**transaction,
+    AddAuditEntryData {
+      actor_token_id: principal.token_id,
+      actor: principal.name.clone(),
+      route,
+      body: json!(body),
+      job_ids,
+      before,
+      after: json!(after),
+    },
+  )
+  .await?;
+  Ok(())
+}
@@ -0,0 +1,92 @@
+use crate::{
+  crontab::{parse_crontab, Crontab},
+  errors::HttpError,
+  middlewares::RequireViewer,
+  models::KnownCrontab,
+  repositories::find_known_crontabs,
+};
+use actix_web::{
+  post,
+  web::{scope, Data, Query},
+  HttpResponse, Scope,
+};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+
+pub fn crontabs_service() -> Scope {
+  scope("/crontabs").service(validate_crontab_route)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateCrontabQuery {
+  pub next_runs: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidCrontabLine {
+  pub line: usize,
+  #[serde(flatten)]
+  pub crontab: Crontab,
+  pub next_runs: Vec<DateTime<Utc>>,
+  /// The `known_crontabs` row of the same identifier, once the worker ran it
+  pub known_crontab: Option<KnownCrontab>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidCrontabLine {
+  pub line: usize,
+  pub source: String,
+  pub message: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateCrontabResult {
+  pub valid: bool,
+  pub crontabs: Vec<ValidCrontabLine>,
+  pub errors: Vec<InvalidCrontabLine>,
+}
+
+/// Validates a crontab file sent as the raw request body.
+#[post("/validate", wrap = "RequireViewer")]
+pub async fn validate_crontab_route(
+  pool: Data<Pool>,
+  query: Query<ValidateCrontabQuery>,
+  source: String,
+) -> Result<HttpResponse, HttpError> {
+  let next_runs = query.next_runs.unwrap_or(5).min(50);
+  let now = Utc::now();
+  let known_crontabs = find_known_crontabs(&pool.get().await?).await?;
+  let lines: Vec<&str> = source.lines().collect();
+
+  let mut crontabs = Vec::new();
+  let mut errors = Vec::new();
+  for (line, crontab) in parse_crontab(&source) {
+    match crontab {
+      Ok(crontab) => crontabs.push(ValidCrontabLine {
+        line,
+        next_runs: crontab.schedule.upcoming(&now, next_runs),
+        known_crontab: known_crontabs
+          .iter()
+          .find(|known_crontab| known_crontab.identifier == crontab.identifier)
+          .cloned(),
+        crontab,
+      }),
+      Err(message) => errors.push(InvalidCrontabLine {
+        line,
+        source: String::from(lines[line - 1].trim()),
+        message,
+      }),
+    }
+  }
+
+  Ok(HttpResponse::Ok().json(ValidateCrontabResult {
+    valid: errors.is_empty(),
+    crontabs,
+    errors,
+  }))
+}
@@ -0,0 +1,176 @@
+use crate::{
+  errors::HttpError,
+  middlewares::{Principal, RequireOperator, RequireViewer},
+  repositories::{
+    find_job_queues, find_locked_job_queues, pause_job_queue, resume_job_queue, unlock_job_queues,
+  },
+  services::{
+    audit_service::audit,
+    job_service::{bulk_limit_error, validate_min_lock_age},
+  },
+  CONFIG,
+};
+use actix_web::{
+  get, post,
+  web::{scope, Data, HttpRequest, Json, Path},
+  HttpResponse, Scope,
+};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+pub fn job_queues_service() -> Scope {
+  scope("/job-queues")
+    .service(find_job_queues_route)
+    .service(unlock_job_queues_route)
+    .service(pause_job_queue_route)
+    .service(resume_job_queue_route)
+}
+
+fn authorize_job_queue(principal: &Principal, queue_name: &str) -> Result<(), HttpError> {
+  if principal.scope.allows_queue(queue_name) {
+    Ok(())
+  } else {
+    Err(HttpError::forbidden(
+      "FRBSC",
+      Some(json!({ "queueNames": [queue_name] })),
+    ))
+  }
+}
+
+#[get("", wrap = "RequireViewer")]
+pub async fn find_job_queues_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+) -> Result<HttpResponse, HttpError> {
+  let params = serde_qs::from_str(req.query_string())?;
+  let job_queues = find_job_queues(&pool.get().await?, params, &principal.scope).await?;
+  Ok(HttpResponse::Ok().json(job_queues))
+}
+
+/// Queues to unlock, selected by name or by the worker holding them. Only locks
+/// older than `min_lock_age` seconds are cleared.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockJobQueuesBody {
+  pub queue_names: Option<Vec<String>>,
+  pub locked_by: Option<String>,
+  pub min_lock_age: u32,
+}
+
+#[post("/unlock", wrap = "RequireOperator")]
+pub async fn unlock_job_queues_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+  body: Json<UnlockJobQueuesBody>,
+) -> Result<HttpResponse, HttpError> {
+  if body.queue_names.is_some() == body.locked_by.is_some() {
+    return Err(HttpError::bad_request(
+      "IVSEL",
+      Some(json!({ "message": "Exactly one of queueNames or lockedBy is required" })),
+    ));
+  }
+  let min_lock_age = validate_min_lock_age(body.min_lock_age)?;
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+
+  let locked_job_queues = find_locked_job_queues(
+    &*transaction,
+    body.queue_names.as_deref(),
+    body.locked_by.as_deref(),
+    min_lock_age,
+  )
+  .await?;
+  let limit = (*CONFIG).bulk.limit;
+  if locked_job_queues.len() as i64 > limit {
+    return Err(bulk_limit_error(locked_job_queues.len() as i64, limit));
+  }
+  let forbidden_queue_names: Vec<&str> = locked_job_queues
+    .iter()
+    .map(|job_queue| job_queue.queue_name.as_str())
+    .filter(|queue_name| !principal.scope.allows_queue(queue_name))
+    .collect();
+  if !forbidden_queue_names.is_empty() {
+    return Err(HttpError::forbidden(
+      "FRBSC",
+      Some(json!({ "queueNames": forbidden_queue_names })),
+    ));
+  }
+
+  let queue_names: Vec<String> = locked_job_queues
+    .iter()
+    .map(|job_queue| job_queue.queue_name.clone())
+    .collect();
+  let result = unlock_job_queues(&*transaction, &queue_names, min_lock_age).await?;
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &body.0,
+    vec![],
+    Some(json!(locked_job_queues)),
+    &result,
+  )
+  .await?;
+  transaction.commit().await?;
+
+  Ok(HttpResponse::Ok().json(result))
+}
+
+/// Holds the job queue back until resumed. A job already running in the queue
+/// still completes.
+#[post("/{queue_name}/pause", wrap = "RequireOperator")]
+pub async fn pause_job_queue_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+  queue_name: Path<String>,
+) -> Result<HttpResponse, HttpError> {
+  authorize_job_queue(&principal, &queue_name)?;
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+
+  let paused_queue = pause_job_queue(&*transaction, &queue_name, &principal.name).await?;
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &json!({ "queueName": queue_name.as_str() }),
+    vec![],
+    None,
+    &paused_queue,
+  )
+  .await?;
+  transaction.commit().await?;
+
+  Ok(HttpResponse::Ok().json(paused_queue))
+}
+
+#[post("/{queue_name}/resume", wrap = "RequireOperator")]
+pub async fn resume_job_queue_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+  queue_name: Path<String>,
+) -> Result<HttpResponse, HttpError> {
+  authorize_job_queue(&principal, &queue_name)?;
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+
+  let paused_queue = resume_job_queue(&*transaction, &queue_name).await?;
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &json!({ "queueName": queue_name.as_str() }),
+    vec![],
+    Some(json!(paused_queue)),
+    &json!(null),
+  )
+  .await?;
+  transaction.commit().await?;
+
+  Ok(HttpResponse::Ok().json(paused_queue))
+}
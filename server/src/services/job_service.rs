@@ -1,103 +1,1070 @@
 use crate::{
   errors::HttpError,
-  models::AddJobData,
+  job_events::{JobEvent, JobEvents},
+  middlewares::{Principal, RequireAdmin, RequireOperator, RequireViewer},
+  models::{AddJobData, Job, UpdateJobData},
   repositories::{
-    add_job, complete_jobs, find_jobs, permanently_fail_jobs, remove_job, reschedule_jobs,
-    RescheduleJobsData,
+    add_job, add_jobs, complete_jobs, count_jobs, find_job_by_id, find_job_by_key, find_job_ids,
+    find_jobs, find_jobs_by_ids, find_locked_jobs, permanently_fail_jobs, remove_job,
+    reschedule_jobs, retry_jobs, stream_jobs, unlock_jobs, update_job, FindJobsFilters,
+    FindJobsParams, JobOrderField, RepositoryError, RepositoryOrderList, RescheduleJobsData,
   },
+  services::audit_service::audit,
+  CONFIG,
 };
 use actix_web::{
-  get, post,
-  web::{scope, Data, HttpRequest, Json},
+  get,
+  http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, ETAG, IF_MATCH},
+  patch, post, rt,
+  web::{scope, Bytes, Data, HttpRequest, Json, Path, PayloadConfig, Query},
   HttpResponse, Scope,
 };
-use deadpool_postgres::Pool;
-use serde::Deserialize;
+use deadpool_postgres::{Client, Pool};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{borrow::Cow, lazy::SyncLazy, sync::Arc, time::Duration};
+use tokio::{
+  sync::{broadcast::error::RecvError, mpsc::channel, Semaphore},
+  time::interval,
+};
+use tokio_postgres::{GenericClient, NoTls};
+use tracing::error;
+
+const JOB_EVENTS_HEARTBEAT: Duration = Duration::from_secs(15);
+const EXPORT_BATCH_SIZE: i32 = 500;
+const IMPORT_BATCH_SIZE: usize = 100;
+const IMPORT_PAYLOAD_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Exports running at once, see `ExportConfig::concurrency`.
+static EXPORT_PERMITS: SyncLazy<Arc<Semaphore>> =
+  SyncLazy::new(|| Arc::new(Semaphore::new((*CONFIG).export.concurrency)));
 
 pub fn jobs_service() -> Scope {
   scope("/jobs")
     .service(find_jobs_route)
+    .service(job_events_route)
+    .service(export_jobs_route)
     .service(add_job_route)
+    // Only imports get the larger body limit
+    .service(
+      scope("/import")
+        .app_data(PayloadConfig::new(IMPORT_PAYLOAD_LIMIT))
+        .service(import_jobs_route),
+    )
     .service(complete_jobs_route)
     .service(permanently_fail_jobs_route)
     .service(reschedule_jobs_route)
+    .service(retry_jobs_route)
+    .service(unlock_jobs_route)
     .service(remove_job_route)
+    .service(find_job_by_key_route)
+    // Must come after every other single segment GET route
+    .service(find_job_by_id_route)
+    .service(update_job_route)
 }
 
-#[get("")]
+#[get("", wrap = "RequireViewer")]
 pub async fn find_jobs_route(
   req: HttpRequest,
+  principal: Principal,
   pool: Data<Pool>,
 ) -> Result<HttpResponse, HttpError> {
-  let params = serde_qs::from_str(req.query_string())?;
+  let params = serde_qs::from_str::<FindJobsParams>(req.query_string())?.scoped(&principal.scope);
   let jobs = find_jobs(&pool.get().await?, params).await?;
   Ok(HttpResponse::Ok().json(jobs))
 }
 
-#[post("")]
+fn job_event_frame(event: &JobEvent) -> Bytes {
+  Bytes::from(format!(
+    "event: {}\ndata: {}\n\n",
+    event.channel,
+    serde_json::to_string(event).unwrap_or_default()
+  ))
+}
+
+/// Notifications do not tell which job was inserted, so tokens with a scope
+/// may not subscribe to them.
+#[get("/events", wrap = "RequireViewer")]
+pub async fn job_events_route(
+  principal: Principal,
+  job_events: Data<JobEvents>,
+) -> Result<HttpResponse, HttpError> {
+  if principal.scope.is_restricted() {
+    return Err(HttpError::forbidden(
+      "FRBSC",
+      Some(json!({ "message": "Job events are not available to scoped tokens" })),
+    ));
+  }
+  let events = stream::unfold(job_events.subscribe(), |mut receiver| async move {
+    let event = match receiver.recv().await {
+      Ok(event) => event,
+      // Slow subscribers are told to resynchronize instead of being dropped
+      Err(RecvError::Lagged(_)) => JobEvent {
+        channel: String::from("lagged"),
+        payload: String::new(),
+      },
+      Err(RecvError::Closed) => return None,
+    };
+    Some((job_event_frame(&event), receiver))
+  });
+  let heartbeats = stream::unfold(interval(JOB_EVENTS_HEARTBEAT), |mut heartbeat| async {
+    heartbeat.tick().await;
+    Some((Bytes::from_static(b": heartbeat\n\n"), heartbeat))
+  });
+
+  let body = stream::once(async { Bytes::from_static(b"retry: 5000\n\n") })
+    .chain(stream::select(events, heartbeats))
+    .map(Ok::<_, actix_web::Error>);
+
+  Ok(
+    HttpResponse::Ok()
+      .insert_header((CONTENT_TYPE, "text/event-stream"))
+      .insert_header((CACHE_CONTROL, "no-cache"))
+      .streaming(body),
+  )
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+  Ndjson,
+  Csv,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportJobsParams {
+  pub format: Option<ExportFormat>,
+  pub order: Option<RepositoryOrderList<JobOrderField>>,
+  pub filters: Option<FindJobsFilters>,
+}
+
+const JOB_CSV_HEADER: &str = "id,queue_name,task_identifier,payload,priority,run_at,attempts,\
+                              max_attempts,last_error,created_at,updated_at,key,locked_at,\
+                              locked_by,revision,flags,state\n";
+
+/// Quotes a CSV field when it contains a separator, a quote or a line break.
+fn csv_field(value: &str) -> Cow<'_, str> {
+  if value.contains(&[',', '"', '\n', '\r'][..]) {
+    Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+  } else {
+    Cow::Borrowed(value)
+  }
+}
+
+fn job_csv_line(job: &Job) -> String {
+  let fields = [
+    job.id.to_string(),
+    job.queue_name.clone().unwrap_or_default(),
+    job.task_identifier.clone(),
+    job.payload.to_string(),
+    job.priority.to_string(),
+    job.run_at.to_rfc3339(),
+    job.attempts.to_string(),
+    job.max_attempts.to_string(),
+    job.last_error.clone().unwrap_or_default(),
+    job.created_at.to_rfc3339(),
+    job.updated_at.to_rfc3339(),
+    job.key.clone().unwrap_or_default(),
+    job
+      .locked_at
+      .map(|locked_at| locked_at.to_rfc3339())
+      .unwrap_or_default(),
+    job.locked_by.clone().unwrap_or_default(),
+    job.revision.to_string(),
+    job
+      .flags
+      .as_ref()
+      .map(|flags| flags.to_string())
+      .unwrap_or_default(),
+    String::from(job.state().as_str()),
+  ];
+  let mut line = fields
+    .iter()
+    .map(|field| csv_field(field))
+    .collect::<Vec<_>>()
+    .join(",");
+  line.push('\n');
+  line
+}
+
+/// Opens a connection outside of the pool, so that long exports do not starve
+/// the other routes of pooled connections.
+async fn export_connection() -> Result<tokio_postgres::Client, HttpError> {
+  let pg_config = (*CONFIG).pg.get_pg_config().map_err(|error| {
+    error!(
+      error = format!("{:?}", error).as_str(),
+      "Error while creating export connection config"
+    );
+    HttpError::internal_server_error("PGERR", None)
+  })?;
+  let (client, connection) = pg_config.connect(NoTls).await?;
+  rt::spawn(async move {
+    if let Err(error) = connection.await {
+      error!(
+        error = format!("{:?}", error).as_str(),
+        "Export connection failed"
+      );
+    }
+  });
+  Ok(client)
+}
+
+/// Streams every job matching the filters and the principal scope, read by
+/// batches from a portal on a connection of its own. At most
+/// `EXPORT_CONCURRENCY` exports run at once, the others are refused.
+#[get("/export", wrap = "RequireViewer")]
+pub async fn export_jobs_route(
+  req: HttpRequest,
+  principal: Principal,
+) -> Result<HttpResponse, HttpError> {
+  let params: ExportJobsParams = serde_qs::from_str(req.query_string())?;
+  let format = params.format.unwrap_or(ExportFormat::Ndjson);
+  let filters = params.filters.unwrap_or_default().scoped(&principal.scope);
+  let permit = EXPORT_PERMITS.clone().try_acquire_owned().map_err(|_| {
+    HttpError::service_unavailable(
+      "EXBSY",
+      Some(json!({ "message": "Too many exports are running, retry later" })),
+    )
+  })?;
+  let mut client = export_connection().await?;
+
+  // Only a couple of batches are buffered, the reader waits for slow clients
+  let (sender, mut receiver) = channel(2);
+  rt::spawn(async move {
+    // Held until the export is done or its client went away
+    let _permit = permit;
+    let order = params.order.unwrap_or_default();
+    if let Err(error) = stream_jobs(&mut client, &filters, &order, EXPORT_BATCH_SIZE, &sender).await
+    {
+      error!(
+        error = format!("{:?}", error).as_str(),
+        "Error while exporting jobs"
+      );
+      sender.send(Err(error)).await.ok();
+    }
+  });
+
+  let header = match format {
+    ExportFormat::Ndjson => None,
+    ExportFormat::Csv => Some(Bytes::from_static(JOB_CSV_HEADER.as_bytes())),
+  };
+  let batches = stream::poll_fn(move |context| receiver.poll_recv(context)).map(move |batch| {
+    let jobs = batch.map_err(|error| actix_web::Error::from(HttpError::from(error)))?;
+    let mut chunk = String::new();
+    for job in jobs.iter() {
+      match format {
+        ExportFormat::Ndjson => {
+          let line = serde_json::to_string(job)
+            .map_err(|error| HttpError::from(RepositoryError::MappingError(error)))?;
+          chunk.push_str(&line);
+          chunk.push('\n');
+        }
+        ExportFormat::Csv => chunk.push_str(&job_csv_line(job)),
+      }
+    }
+    Ok::<_, actix_web::Error>(Bytes::from(chunk))
+  });
+
+  let (content_type, file_name) = match format {
+    ExportFormat::Ndjson => ("application/x-ndjson", "jobs.ndjson"),
+    ExportFormat::Csv => ("text/csv; charset=utf-8", "jobs.csv"),
+  };
+  Ok(
+    HttpResponse::Ok()
+      .insert_header((CONTENT_TYPE, content_type))
+      .insert_header((
+        CONTENT_DISPOSITION,
+        format!("attachment; filename=\"{}\"", file_name),
+      ))
+      .streaming(stream::iter(header.map(Ok)).chain(batches)),
+  )
+}
+
+#[get("/{job_id}", wrap = "RequireViewer")]
+pub async fn find_job_by_id_route(
+  principal: Principal,
+  pool: Data<Pool>,
+  job_id: Path<i64>,
+) -> Result<HttpResponse, HttpError> {
+  let job = find_job_by_id(&**pool.get().await?, job_id.into_inner()).await?;
+  authorize_job_read(&principal, &job)?;
+  Ok(
+    HttpResponse::Ok()
+      .insert_header((ETAG, format!("\"{}\"", job.revision)))
+      .json(job),
+  )
+}
+
+/// Reads the expected revision from an `If-Match: "<revision>"` header.
+fn if_match_revision(req: &HttpRequest) -> Result<Option<i32>, HttpError> {
+  let if_match = match req.headers().get(IF_MATCH) {
+    Some(if_match) => if_match,
+    None => return Ok(None),
+  };
+  if_match
+    .to_str()
+    .ok()
+    .map(|value| value.trim().trim_start_matches("W/").trim_matches('"'))
+    .and_then(|value| value.parse().ok())
+    .map(Some)
+    .ok_or_else(|| HttpError::bad_request("IVIFM", None))
+}
+
+fn validate_job_fields(
+  task_identifier: Option<&str>,
+  queue_name: Option<&str>,
+  max_attempts: Option<i64>,
+) -> Result<(), HttpError> {
+  if let Some(task_identifier) = task_identifier {
+    if task_identifier.is_empty() || task_identifier.len() > 128 {
+      return Err(HttpError::bad_request(
+        "IVTID",
+        Some(json!({ "message": "Task identifier must be between 1 and 128 characters" })),
+      ));
+    }
+  }
+  if let Some(queue_name) = queue_name {
+    if queue_name.len() > 128 {
+      return Err(HttpError::bad_request(
+        "IVQNM",
+        Some(json!({ "message": "Job queue name is too long (max length: 128)" })),
+      ));
+    }
+  }
+  if let Some(max_attempts) = max_attempts {
+    if max_attempts < 1 || max_attempts > i64::from(i32::MAX) {
+      return Err(HttpError::bad_request(
+        "IVMAT",
+        Some(json!({ "message": "Job maximum attempts must be between 1 and 2147483647" })),
+      ));
+    }
+  }
+  Ok(())
+}
+
+fn validate_update_job_data(data: &UpdateJobData) -> Result<(), HttpError> {
+  validate_job_fields(
+    data.task_identifier.as_deref(),
+    data.queue_name.as_deref(),
+    data.max_attempts.map(i64::from),
+  )
+}
+
+#[patch("/{job_id}", wrap = "RequireOperator")]
+pub async fn update_job_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+  job_id: Path<i64>,
+  data: Json<UpdateJobData>,
+) -> Result<HttpResponse, HttpError> {
+  let data = data.into_inner();
+  validate_update_job_data(&data)?;
+  let revision = match (data.revision, if_match_revision(&req)?) {
+    (Some(revision), Some(if_match)) if revision != if_match => {
+      return Err(HttpError::bad_request("IVREV", None))
+    }
+    (Some(revision), _) | (None, Some(revision)) => revision,
+    (None, None) => {
+      return Err(HttpError::bad_request(
+        "RVREQ",
+        Some(json!({ "message": "The expected revision is required" })),
+      ))
+    }
+  };
+
+  let job_id = job_id.into_inner();
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+  let before = find_job_by_id(&*transaction, job_id).await?;
+  authorize_job_target(
+    &principal,
+    before.queue_name.as_deref(),
+    &before.task_identifier,
+  )?;
+  authorize_job_target(
+    &principal,
+    data.queue_name.as_deref().or(before.queue_name.as_deref()),
+    data
+      .task_identifier
+      .as_ref()
+      .unwrap_or(&before.task_identifier),
+  )?;
+
+  let job = update_job(&*transaction, job_id, revision, data.clone()).await?;
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &data,
+    vec![job_id],
+    Some(json!(before)),
+    &job,
+  )
+  .await?;
+  transaction.commit().await?;
+  Ok(
+    HttpResponse::Ok()
+      .insert_header((ETAG, format!("\"{}\"", job.revision)))
+      .json(job),
+  )
+}
+
+#[get("/by-key/{job_key:.*}", wrap = "RequireViewer")]
+pub async fn find_job_by_key_route(
+  principal: Principal,
+  pool: Data<Pool>,
+  job_key: Path<String>,
+) -> Result<HttpResponse, HttpError> {
+  let job = find_job_by_key(&**pool.get().await?, job_key.into_inner()).await?;
+  authorize_job_read(&principal, &job)?;
+  Ok(HttpResponse::Ok().json(job))
+}
+
+#[post("", wrap = "RequireOperator")]
 pub async fn add_job_route(
+  req: HttpRequest,
+  principal: Principal,
   pool: Data<Pool>,
   data: Json<AddJobData>,
 ) -> Result<HttpResponse, HttpError> {
-  let job = add_job(&pool.get().await?, data.0).await?;
+  authorize_job_target(
+    &principal,
+    data.queue_name.as_deref(),
+    &data.task_identifier,
+  )?;
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+  let job = add_job(&*transaction, data.0.clone()).await?;
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &data.0,
+    vec![job.id],
+    None,
+    &job,
+  )
+  .await?;
+  transaction.commit().await?;
   Ok(HttpResponse::Ok().json(job))
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct ImportJobsQuery {
+  pub dry_run: Option<bool>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportJobLine {
+  pub line: usize,
+  /// Left out on dry runs, the jobs being rolled back
+  pub job_id: Option<i64>,
+  pub error: Option<HttpError>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportJobsResult {
+  pub dry_run: bool,
+  pub created_count: usize,
+  pub error_count: usize,
+  /// Set when a batch failed, its lines and the following ones being left out
+  pub aborted: bool,
+  pub lines: Vec<ImportJobLine>,
+}
+
+/// Parses and checks one NDJSON line of an import.
+fn parse_import_line(principal: &Principal, line: &str) -> Result<AddJobData, HttpError> {
+  let data: AddJobData = serde_json::from_str(line).map_err(|error| {
+    HttpError::bad_request("IVJSN", Some(json!({ "message": error.to_string() })))
+  })?;
+  validate_job_fields(
+    Some(&data.task_identifier),
+    data.queue_name.as_deref(),
+    data.max_attempts.map(i64::from),
+  )?;
+  authorize_job_target(principal, data.queue_name.as_deref(), &data.task_identifier)?;
+  Ok(data)
+}
+
+fn import_batch_error(message: &str) -> HttpError {
+  HttpError::internal_server_error("IMBAT", Some(json!({ "message": message })))
+}
+
+/// Adds a batch of import lines in a transaction of its own, rolled back on dry
+/// runs and audited otherwise.
+async fn import_job_batch(
+  client: &mut Client,
+  req: &HttpRequest,
+  principal: &Principal,
+  dry_run: bool,
+  batch: &[(usize, AddJobData)],
+) -> Result<Vec<ImportJobLine>, HttpError> {
+  let jobs: Vec<AddJobData> = batch.iter().map(|(_, data)| data.clone()).collect();
+  let mut transaction = client.transaction().await?;
+  let results = add_jobs(&mut transaction, &jobs).await?;
+  let mut lines = Vec::with_capacity(batch.len());
+  let mut job_ids = Vec::with_capacity(batch.len());
+  for ((line, _), result) in batch.iter().zip(results) {
+    lines.push(match result {
+      Ok(job) => {
+        job_ids.push(job.id);
+        ImportJobLine {
+          line: *line,
+          job_id: Some(job.id).filter(|_| !dry_run),
+          error: None,
+        }
+      }
+      Err(message) => ImportJobLine {
+        line: *line,
+        job_id: None,
+        error: Some(HttpError::bad_request(
+          "IVJOB",
+          Some(json!({ "message": message })),
+        )),
+      },
+    });
+  }
+
+  if dry_run {
+    transaction.rollback().await?;
+  } else {
+    // The batch summary is audited rather than its lines
+    let created_count = job_ids.len();
+    audit(
+      &transaction,
+      req,
+      principal,
+      &json!({ "dryRun": dry_run, "lineCount": batch.len() }),
+      job_ids,
+      None,
+      &json!({ "createdCount": created_count, "errorCount": batch.len() - created_count }),
+    )
+    .await?;
+    transaction.commit().await?;
+  }
+
+  Ok(lines)
+}
+
+/// Adds the jobs of an NDJSON body, one `AddJobData` per line, by batches of
+/// `IMPORT_BATCH_SIZE` per transaction, each batch being audited in its own.
+/// Invalid lines are reported and skipped, `dryRun` checks every line against
+/// the database then rolls back. When a batch fails, the batches before it
+/// stay imported and the lines of the others are reported as not imported.
+#[post("", wrap = "RequireOperator")]
+pub async fn import_jobs_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+  query: Query<ImportJobsQuery>,
+  body: Bytes,
+) -> Result<HttpResponse, HttpError> {
+  let dry_run = query.dry_run.unwrap_or(false);
+  let source = std::str::from_utf8(&body).map_err(|_| {
+    HttpError::bad_request(
+      "IVJSN",
+      Some(json!({ "message": "The body must be UTF-8 encoded NDJSON" })),
+    )
+  })?;
+  let lines: Vec<(usize, &str)> = source
+    .lines()
+    .enumerate()
+    .map(|(index, line)| (index + 1, line.trim()))
+    .filter(|(_, line)| !line.is_empty())
+    .collect();
+  let limit = (*CONFIG).bulk.limit;
+  if lines.len() as i64 > limit {
+    return Err(bulk_limit_error(lines.len() as i64, limit));
+  }
+
+  let mut report = Vec::with_capacity(lines.len());
+  let mut valid = Vec::with_capacity(lines.len());
+  for (line, source) in lines {
+    match parse_import_line(&principal, source) {
+      Ok(data) => valid.push((line, data)),
+      Err(error) => report.push(ImportJobLine {
+        line,
+        job_id: None,
+        error: Some(error),
+      }),
+    }
+  }
+
+  let mut client = pool.get().await?;
+  let mut aborted = false;
+  for batch in valid.chunks(IMPORT_BATCH_SIZE) {
+    if aborted {
+      report.extend(batch.iter().map(|(line, _)| ImportJobLine {
+        line: *line,
+        job_id: None,
+        error: Some(import_batch_error(
+          "Not imported, as a previous batch failed",
+        )),
+      }));
+      continue;
+    }
+    match import_job_batch(&mut client, &req, &principal, dry_run, batch).await {
+      Ok(lines) => report.extend(lines),
+      Err(error) => {
+        error!(
+          error = format!("{:?}", error).as_str(),
+          "Error while importing jobs"
+        );
+        aborted = true;
+        report.extend(batch.iter().map(|(line, _)| ImportJobLine {
+          line: *line,
+          job_id: None,
+          error: Some(import_batch_error(
+            "Not imported, as the batch of this line failed",
+          )),
+        }));
+      }
+    }
+  }
+  report.sort_by_key(|line| line.line);
+
+  let error_count = report.iter().filter(|line| line.error.is_some()).count();
+  let result = ImportJobsResult {
+    dry_run,
+    created_count: if dry_run {
+      0
+    } else {
+      report.len() - error_count
+    },
+    error_count,
+    aborted,
+    lines: report,
+  };
+
+  Ok(HttpResponse::Ok().json(result))
+}
+
+fn job_scope_error(job_ids: Vec<i64>) -> HttpError {
+  HttpError::forbidden("FRBSC", Some(json!({ "jobIds": job_ids })))
+}
+
+/// Hides the jobs out of the principal scope as if they did not exist.
+fn authorize_job_read(principal: &Principal, job: &Job) -> Result<(), HttpError> {
+  if principal
+    .scope
+    .allows(job.queue_name.as_deref(), &job.task_identifier)
+  {
+    Ok(())
+  } else {
+    Err(RepositoryError::NotFound.into())
+  }
+}
+
+/// Checks a job created or moved to `queue_name` / `task_identifier` stays in
+/// the principal scope.
+pub fn authorize_job_target(
+  principal: &Principal,
+  queue_name: Option<&str>,
+  task_identifier: &str,
+) -> Result<(), HttpError> {
+  if principal.scope.allows(queue_name, task_identifier) {
+    Ok(())
+  } else {
+    Err(HttpError::forbidden(
+      "FRBSC",
+      Some(json!({ "queueName": queue_name, "taskIdentifier": task_identifier })),
+    ))
+  }
+}
+
+/// Checks every existing job among `job_ids` is in the principal scope.
+pub async fn authorize_job_ids<C: GenericClient>(
+  client: &C,
+  principal: &Principal,
+  job_ids: &[i64],
+) -> Result<(), HttpError> {
+  if !principal.scope.is_restricted() {
+    return Ok(());
+  }
+  let forbidden_job_ids: Vec<i64> = find_jobs_by_ids(client, job_ids)
+    .await?
+    .into_iter()
+    .filter(|job| {
+      !principal
+        .scope
+        .allows(job.queue_name.as_deref(), &job.task_identifier)
+    })
+    .map(|job| job.id)
+    .collect();
+  if forbidden_job_ids.is_empty() {
+    Ok(())
+  } else {
+    Err(job_scope_error(forbidden_job_ids))
+  }
+}
+
+/// Jobs targeted by a bulk action: either explicit ids, or every job matching
+/// `filters`. Selecting by filters first returns a preview of the affected
+/// count, which must then be sent back as `expected_count` to run the action.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobSelector {
+  pub job_ids: Option<Vec<i64>>,
+  pub filters: Option<FindJobsFilters>,
+  pub expected_count: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkActionPreview {
+  pub affected_count: i64,
+  pub limit: i64,
+}
+
+pub enum SelectedJobs {
+  Preview(BulkActionPreview),
+  Jobs(Vec<i64>),
+}
+
+pub fn bulk_limit_error(affected_count: i64, limit: i64) -> HttpError {
+  HttpError::bad_request(
+    "BLKLM",
+    Some(json!({ "affectedCount": affected_count, "limit": limit })),
+  )
+}
+
+fn bulk_conflict_error(expected_count: i64, affected_count: i64) -> HttpError {
+  HttpError::conflict(
+    "BLKCF",
+    Some(json!({ "expectedCount": expected_count, "affectedCount": affected_count })),
+  )
+}
+
+/// Resolves the selected jobs, all of which must be in the principal scope.
+/// Filters only ever match jobs in scope.
+pub async fn select_jobs<C: GenericClient>(
+  client: &C,
+  principal: &Principal,
+  selector: &JobSelector,
+) -> Result<SelectedJobs, HttpError> {
+  let limit = (*CONFIG).bulk.limit;
+
+  let job_ids = match (&selector.job_ids, &selector.filters) {
+    (Some(job_ids), None) => {
+      if job_ids.len() as i64 > limit {
+        return Err(bulk_limit_error(job_ids.len() as i64, limit));
+      }
+      job_ids.clone()
+    }
+    (None, Some(filters)) => {
+      let filters = filters.clone().scoped(&principal.scope);
+      let affected_count = count_jobs(client, &filters).await?;
+      if affected_count > limit {
+        return Err(bulk_limit_error(affected_count, limit));
+      }
+      match selector.expected_count {
+        None => {
+          return Ok(SelectedJobs::Preview(BulkActionPreview {
+            affected_count,
+            limit,
+          }))
+        }
+        Some(expected_count) if expected_count != affected_count => {
+          return Err(bulk_conflict_error(expected_count, affected_count))
+        }
+        // One extra id is fetched to notice jobs matching since the count
+        Some(expected_count) => {
+          let job_ids = find_job_ids(client, &filters, expected_count + 1).await?;
+          if job_ids.len() as i64 != expected_count {
+            return Err(bulk_conflict_error(expected_count, job_ids.len() as i64));
+          }
+          job_ids
+        }
+      }
+    }
+    _ => {
+      return Err(HttpError::bad_request(
+        "IVSEL",
+        Some(json!({ "message": "Exactly one of jobIds or filters is required" })),
+      ))
+    }
+  };
+
+  authorize_job_ids(client, principal, &job_ids).await?;
+  Ok(SelectedJobs::Jobs(job_ids))
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CompleteJobBody {
-  pub job_ids: Vec<i64>,
+  #[serde(flatten)]
+  pub selector: JobSelector,
 }
 
-#[post("/complete")]
+#[post("/complete", wrap = "RequireAdmin")]
 pub async fn complete_jobs_route(
+  req: HttpRequest,
+  principal: Principal,
   pool: Data<Pool>,
   body: Json<CompleteJobBody>,
 ) -> Result<HttpResponse, HttpError> {
-  let completed_jobs = complete_jobs(&pool.get().await?, &body.job_ids).await?;
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+  let job_ids = match select_jobs(&*transaction, &principal, &body.selector).await? {
+    SelectedJobs::Preview(preview) => return Ok(HttpResponse::Ok().json(preview)),
+    SelectedJobs::Jobs(job_ids) => job_ids,
+  };
+
+  let before = json!(find_jobs_by_ids(&*transaction, &job_ids).await?);
+  let completed_jobs = complete_jobs(&*transaction, &job_ids).await?;
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &body.0,
+    job_ids,
+    Some(before),
+    &completed_jobs,
+  )
+  .await?;
+  transaction.commit().await?;
   Ok(HttpResponse::Ok().json(completed_jobs))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PermanentlyFailJobsBody {
-  pub job_ids: Vec<i64>,
+  #[serde(flatten)]
+  pub selector: JobSelector,
   pub error_messages: String,
 }
 
-#[post("/permanently-fail")]
+#[post("/permanently-fail", wrap = "RequireAdmin")]
 pub async fn permanently_fail_jobs_route(
+  req: HttpRequest,
+  principal: Principal,
   pool: Data<Pool>,
   body: Json<PermanentlyFailJobsBody>,
 ) -> Result<HttpResponse, HttpError> {
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+  let job_ids = match select_jobs(&*transaction, &principal, &body.selector).await? {
+    SelectedJobs::Preview(preview) => return Ok(HttpResponse::Ok().json(preview)),
+    SelectedJobs::Jobs(job_ids) => job_ids,
+  };
+
+  let before = json!(find_jobs_by_ids(&*transaction, &job_ids).await?);
   let permanently_failed_jobs =
-    permanently_fail_jobs(&pool.get().await?, &body.job_ids, &body.error_messages).await?;
+    permanently_fail_jobs(&*transaction, &job_ids, &body.error_messages).await?;
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &body.0,
+    job_ids,
+    Some(before),
+    &permanently_failed_jobs,
+  )
+  .await?;
+  transaction.commit().await?;
 
   Ok(HttpResponse::Ok().json(permanently_failed_jobs))
 }
 
-#[post("/reschedule")]
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RescheduleJobsBody {
+  #[serde(flatten)]
+  pub selector: JobSelector,
+  #[serde(flatten)]
+  pub data: RescheduleJobsData,
+}
+
+#[post("/reschedule", wrap = "RequireOperator")]
 pub async fn reschedule_jobs_route(
+  req: HttpRequest,
+  principal: Principal,
   pool: Data<Pool>,
-  body: Json<RescheduleJobsData>,
+  body: Json<RescheduleJobsBody>,
 ) -> Result<HttpResponse, HttpError> {
-  let result = reschedule_jobs(&pool.get().await?, body.0).await?;
+  let body = body.into_inner();
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+  let job_ids = match select_jobs(&*transaction, &principal, &body.selector).await? {
+    SelectedJobs::Preview(preview) => return Ok(HttpResponse::Ok().json(preview)),
+    SelectedJobs::Jobs(job_ids) => job_ids,
+  };
+
+  let before = json!(find_jobs_by_ids(&*transaction, &job_ids).await?);
+  let result = reschedule_jobs(&*transaction, &job_ids, body.data.clone()).await?;
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &body,
+    job_ids,
+    Some(before),
+    &result,
+  )
+  .await?;
+  transaction.commit().await?;
 
   Ok(HttpResponse::Ok().json(result))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryJobsBody {
+  #[serde(flatten)]
+  pub selector: JobSelector,
+  /// Only ever raises the maximum attempts of the retried jobs
+  pub max_attempts: Option<i64>,
+}
+
+#[post("/retry", wrap = "RequireOperator")]
+pub async fn retry_jobs_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+  body: Json<RetryJobsBody>,
+) -> Result<HttpResponse, HttpError> {
+  validate_job_fields(None, None, body.max_attempts)?;
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+  let job_ids = match select_jobs(&*transaction, &principal, &body.selector).await? {
+    SelectedJobs::Preview(preview) => return Ok(HttpResponse::Ok().json(preview)),
+    SelectedJobs::Jobs(job_ids) => job_ids,
+  };
+
+  let before = json!(find_jobs_by_ids(&*transaction, &job_ids).await?);
+  let max_attempts = body.max_attempts.map(|max_attempts| max_attempts as i32);
+  let result = retry_jobs(&*transaction, &job_ids, max_attempts).await?;
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &body.0,
+    job_ids,
+    Some(before),
+    &result,
+  )
+  .await?;
+  transaction.commit().await?;
+
+  Ok(HttpResponse::Ok().json(result))
+}
+
+/// Rejects lock ages below `UNLOCK_MINAGE`, so that a job still running is not
+/// unlocked and run twice.
+pub fn validate_min_lock_age(min_lock_age: u32) -> Result<i64, HttpError> {
+  let min = (*CONFIG).unlock.minage;
+  if min_lock_age < min {
+    return Err(HttpError::bad_request(
+      "IVLKA",
+      Some(json!({
+        "message": format!("The minimum lock age must be at least {} seconds", min),
+        "min": min
+      })),
+    ));
+  }
+  Ok(min_lock_age as i64)
+}
+
+/// Jobs to unlock, selected by ids or by the worker holding them. Only locks
+/// older than `min_lock_age` seconds are cleared.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockJobsBody {
+  pub job_ids: Option<Vec<i64>>,
+  pub locked_by: Option<String>,
+  pub min_lock_age: u32,
+}
+
+#[post("/unlock", wrap = "RequireOperator")]
+pub async fn unlock_jobs_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+  body: Json<UnlockJobsBody>,
+) -> Result<HttpResponse, HttpError> {
+  if body.job_ids.is_some() == body.locked_by.is_some() {
+    return Err(HttpError::bad_request(
+      "IVSEL",
+      Some(json!({ "message": "Exactly one of jobIds or lockedBy is required" })),
+    ));
+  }
+  let min_lock_age = validate_min_lock_age(body.min_lock_age)?;
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+
+  let locked_jobs = find_locked_jobs(
+    &*transaction,
+    body.job_ids.as_deref(),
+    body.locked_by.as_deref(),
+    min_lock_age,
+  )
+  .await?;
+  let limit = (*CONFIG).bulk.limit;
+  if locked_jobs.len() as i64 > limit {
+    return Err(bulk_limit_error(locked_jobs.len() as i64, limit));
+  }
+  let job_ids: Vec<i64> = locked_jobs.iter().map(|job| job.id).collect();
+  authorize_job_ids(&*transaction, &principal, &job_ids).await?;
+
+  let result = unlock_jobs(&*transaction, &job_ids, min_lock_age).await?;
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &body.0,
+    job_ids,
+    Some(json!(locked_jobs)),
+    &result,
+  )
+  .await?;
+  transaction.commit().await?;
+
+  Ok(HttpResponse::Ok().json(result))
+}
+
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RemoveJobBody {
   pub job_key: String,
 }
 
-#[post("/remove")]
+#[post("/remove", wrap = "RequireAdmin")]
 pub async fn remove_job_route(
+  req: HttpRequest,
+  principal: Principal,
   pool: Data<Pool>,
   body: Json<RemoveJobBody>,
 ) -> Result<HttpResponse, HttpError> {
-  let result = remove_job(&pool.get().await?, &body.job_key).await?;
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+  let before = match find_job_by_key(&*transaction, &body.job_key).await {
+    Ok(job) => Some(job),
+    Err(RepositoryError::NotFound) => None,
+    Err(error) => return Err(error.into()),
+  };
+  if let Some(job) = &before {
+    authorize_job_target(&principal, job.queue_name.as_deref(), &job.task_identifier)?;
+  }
+
+  let result = remove_job(&*transaction, &body.job_key).await?;
+  let job_ids = result.removed_job.iter().map(|job| job.id).collect();
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &body.0,
+    job_ids,
+    before.map(|job| json!(job)),
+    &result,
+  )
+  .await?;
+  transaction.commit().await?;
 
   Ok(HttpResponse::Ok().json(result))
 }
@@ -0,0 +1,207 @@
+use crate::{
+  crontab::{find_crontab, Crontab},
+  errors::HttpError,
+  middlewares::{Principal, RequireOperator, RequireViewer},
+  models::{Job, KnownCrontab},
+  repositories::{add_job, add_known_crontab_job, find_known_crontab, find_known_crontabs},
+  services::{audit_service::audit, job_service::authorize_job_target},
+  CONFIG,
+};
+use actix_web::{
+  get, post,
+  web::{scope, Data, HttpRequest, Json, Path, Query},
+  HttpResponse, Scope,
+};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+pub fn known_crontabs_service() -> Scope {
+  scope("/known-crontabs")
+    .service(find_known_crontabs_route)
+    .service(trigger_known_crontab_route)
+    .service(backfill_known_crontab_route)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindKnownCrontabsQuery {
+  pub next_runs: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownCrontabDefinition {
+  #[serde(flatten)]
+  pub crontab: &'static Crontab,
+  pub next_runs: Vec<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownCrontabWithDefinition {
+  #[serde(flatten)]
+  pub known_crontab: KnownCrontab,
+  pub definition: Option<KnownCrontabDefinition>,
+}
+
+#[get("", wrap = "RequireViewer")]
+pub async fn find_known_crontabs_route(
+  pool: Data<Pool>,
+  query: Query<FindKnownCrontabsQuery>,
+) -> Result<HttpResponse, HttpError> {
+  let next_runs = query.next_runs.unwrap_or(5).min(50);
+  let now = Utc::now();
+
+  let known_crontabs: Vec<KnownCrontabWithDefinition> = find_known_crontabs(&pool.get().await?)
+    .await?
+    .into_iter()
+    .map(|known_crontab| {
+      let definition =
+        find_crontab(&known_crontab.identifier).map(|crontab| KnownCrontabDefinition {
+          crontab,
+          next_runs: crontab.schedule.upcoming(&now, next_runs),
+        });
+      KnownCrontabWithDefinition {
+        known_crontab,
+        definition,
+      }
+    })
+    .collect();
+
+  Ok(HttpResponse::Ok().json(known_crontabs))
+}
+
+/// Finds the crontab file definition of `identifier`, in the principal scope.
+fn authorize_crontab(
+  principal: &Principal,
+  identifier: &str,
+) -> Result<&'static Crontab, HttpError> {
+  let crontab = find_crontab(identifier).ok_or_else(|| {
+    HttpError::not_found(
+      "NTFND",
+      Some(json!({ "message": "Crontab is not defined in the crontab file" })),
+    )
+  })?;
+  authorize_job_target(
+    principal,
+    crontab.queue_name.as_deref(),
+    &crontab.task_identifier,
+  )?;
+  Ok(crontab)
+}
+
+/// Enqueues the crontab task right away, outside of its schedule.
+#[post("/{identifier}/trigger", wrap = "RequireOperator")]
+pub async fn trigger_known_crontab_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+  identifier: Path<String>,
+) -> Result<HttpResponse, HttpError> {
+  let crontab = authorize_crontab(&principal, &identifier)?;
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+
+  let job = add_job(&*transaction, crontab.job_data(Utc::now(), false)).await?;
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &json!({ "identifier": identifier.as_str() }),
+    vec![job.id],
+    None,
+    &job,
+  )
+  .await?;
+  transaction.commit().await?;
+
+  Ok(HttpResponse::Ok().json(job))
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackfillKnownCrontabBody {
+  pub max_count: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackfillKnownCrontabResult {
+  pub backfilled_jobs: Vec<Job>,
+  /// Whether missed executions remain after the backfilled ones
+  pub has_more: bool,
+}
+
+/// Enqueues one job per execution missed since `last_execution`, oldest
+/// first. Each one moves `last_execution` forward, so calling it again
+/// carries on where the previous call stopped.
+#[post("/{identifier}/backfill", wrap = "RequireOperator")]
+pub async fn backfill_known_crontab_route(
+  req: HttpRequest,
+  principal: Principal,
+  pool: Data<Pool>,
+  identifier: Path<String>,
+  body: Json<BackfillKnownCrontabBody>,
+) -> Result<HttpResponse, HttpError> {
+  let limit = (*CONFIG).bulk.limit;
+  if body.max_count as i64 > limit {
+    return Err(HttpError::bad_request(
+      "BLKLM",
+      Some(json!({ "affectedCount": body.max_count, "limit": limit })),
+    ));
+  }
+  let crontab = authorize_crontab(&principal, &identifier)?;
+  let mut client = pool.get().await?;
+  let transaction = client.transaction().await?;
+  let known_crontab = find_known_crontab(&*transaction, identifier.as_str()).await?;
+
+  let now = Utc::now();
+  let mut missed = Vec::new();
+  let mut cursor = known_crontab
+    .last_execution
+    .unwrap_or(known_crontab.known_since);
+  while missed.len() <= body.max_count {
+    match crontab.schedule.next_after(&cursor) {
+      Some(ts) if ts <= now => {
+        missed.push(ts);
+        cursor = ts;
+      }
+      _ => break,
+    }
+  }
+  let has_more = missed.len() > body.max_count;
+  missed.truncate(body.max_count);
+
+  let mut backfilled_jobs = Vec::with_capacity(missed.len());
+  for ts in missed {
+    if let Some(job) = add_known_crontab_job(
+      &*transaction,
+      identifier.as_str(),
+      ts,
+      crontab.job_data(ts, true),
+    )
+    .await?
+    {
+      backfilled_jobs.push(job);
+    }
+  }
+  let result = BackfillKnownCrontabResult {
+    backfilled_jobs,
+    has_more,
+  };
+  audit(
+    &transaction,
+    &req,
+    &principal,
+    &json!({ "identifier": identifier.as_str(), "maxCount": body.max_count }),
+    result.backfilled_jobs.iter().map(|job| job.id).collect(),
+    None,
+    &result,
+  )
+  .await?;
+  transaction.commit().await?;
+
+  Ok(HttpResponse::Ok().json(result))
+}
@@ -0,0 +1,125 @@
+use crate::{
+  errors::HttpError,
+  metrics::{write_http_metrics, MetricsText},
+  middlewares::{Authentication, Principal},
+  repositories::find_job_metrics,
+};
+use actix_web::{get, web::Data, HttpResponse};
+use deadpool_postgres::Pool;
+use serde_json::json;
+
+/// Metrics cover every queue and task, so they are refused to scoped tokens.
+#[get("/metrics", wrap = "Authentication")]
+pub async fn metrics_route(
+  principal: Principal,
+  pool: Data<Pool>,
+) -> Result<HttpResponse, HttpError> {
+  if principal.scope.is_restricted() {
+    return Err(HttpError::forbidden(
+      "FRBSC",
+      Some(json!({ "message": "Metrics are not available to scoped tokens" })),
+    ));
+  }
+  let job_metrics = find_job_metrics(&pool.get().await?).await?;
+  let mut text = MetricsText::default();
+
+  text.family(
+    "graphboard_jobs",
+    "gauge",
+    "Jobs by task identifier and state",
+  );
+  for (task_identifier, state, count) in &job_metrics.jobs {
+    text.sample(
+      "graphboard_jobs",
+      &[("task_identifier", task_identifier), ("state", state)],
+      count,
+    );
+  }
+
+  text.family(
+    "graphboard_jobs_past_due",
+    "gauge",
+    "Unlocked jobs with attempts left whose run_at is in the past",
+  );
+  for (task_identifier, count) in &job_metrics.past_due_jobs {
+    text.sample(
+      "graphboard_jobs_past_due",
+      &[("task_identifier", task_identifier)],
+      count,
+    );
+  }
+
+  text.family(
+    "graphboard_jobs_stale_locks",
+    "gauge",
+    "Jobs locked for more than the 4 hours lock expiry",
+  );
+  text.sample(
+    "graphboard_jobs_stale_locks",
+    &[],
+    job_metrics.stale_locked_jobs,
+  );
+
+  text.family(
+    "graphboard_job_queue_jobs",
+    "gauge",
+    "Jobs in each job queue",
+  );
+  for job_queue in &job_metrics.job_queues {
+    text.sample(
+      "graphboard_job_queue_jobs",
+      &[("queue_name", &job_queue.queue_name)],
+      job_queue.job_count,
+    );
+  }
+  text.family(
+    "graphboard_job_queue_locked",
+    "gauge",
+    "Whether each job queue is locked",
+  );
+  for job_queue in &job_metrics.job_queues {
+    text.sample(
+      "graphboard_job_queue_locked",
+      &[("queue_name", &job_queue.queue_name)],
+      job_queue.locked_at.is_some() as u8,
+    );
+  }
+
+  let status = pool.status();
+  text.family(
+    "graphboard_pg_pool_size",
+    "gauge",
+    "Connections currently in the pool",
+  );
+  text.sample("graphboard_pg_pool_size", &[], status.size);
+  text.family(
+    "graphboard_pg_pool_max_size",
+    "gauge",
+    "Maximum size of the pool",
+  );
+  text.sample("graphboard_pg_pool_max_size", &[], status.max_size);
+  text.family(
+    "graphboard_pg_pool_available",
+    "gauge",
+    "Idle connections in the pool",
+  );
+  text.sample("graphboard_pg_pool_available", &[], status.available.max(0));
+  text.family(
+    "graphboard_pg_pool_waiting",
+    "gauge",
+    "Requests waiting for a connection",
+  );
+  text.sample(
+    "graphboard_pg_pool_waiting",
+    &[],
+    (-status.available).max(0),
+  );
+
+  write_http_metrics(&mut text);
+
+  Ok(
+    HttpResponse::Ok()
+      .content_type("text/plain; version=0.0.4")
+      .body(text.into_string()),
+  )
+}
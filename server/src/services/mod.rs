@@ -1,7 +1,27 @@
-use crate::services::job_service::jobs_service;
+use crate::{
+  middlewares::Authentication,
+  services::{
+    api_token_service::api_tokens_service, archived_job_service::archived_jobs_service,
+    audit_service::audit_service, crontab_service::crontabs_service,
+    job_queue_service::job_queues_service, job_service::jobs_service,
+    known_crontab_service::known_crontabs_service, stats_service::stats_service,
+    worker_service::workers_service,
+  },
+};
 use actix_web::{get, web, HttpResponse, Responder, Scope};
 
+pub use metrics_service::metrics_route;
+
+mod api_token_service;
+mod archived_job_service;
+mod audit_service;
+mod crontab_service;
+mod job_queue_service;
 mod job_service;
+mod known_crontab_service;
+mod metrics_service;
+mod stats_service;
+mod worker_service;
 
 #[get("/ping")]
 async fn ping() -> impl Responder {
@@ -9,5 +29,18 @@ async fn ping() -> impl Responder {
 }
 
 pub fn api_services() -> Scope {
-  web::scope("/api").service(ping).service(jobs_service())
+  // The ping stays public for health checks
+  web::scope("/api").service(ping).service(
+    web::scope("")
+      .wrap(Authentication)
+      .service(jobs_service())
+      .service(job_queues_service())
+      .service(known_crontabs_service())
+      .service(crontabs_service())
+      .service(stats_service())
+      .service(workers_service())
+      .service(archived_jobs_service())
+      .service(api_tokens_service())
+      .service(audit_service()),
+  )
 }
@@ -0,0 +1,103 @@
+use crate::{
+  errors::HttpError,
+  middlewares::{Principal, RequireViewer},
+  models::JobSamplePoint,
+  repositories::{find_job_sample_points, find_job_stats},
+};
+use actix_web::{
+  get,
+  web::{scope, Data, Query},
+  HttpResponse, Scope,
+};
+use chrono::{DateTime, Duration, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+const MAX_TIMESERIES_BUCKETS: i64 = 1000;
+
+pub fn stats_service() -> Scope {
+  scope("/stats")
+    .service(find_job_stats_route)
+    .service(find_job_timeseries_route)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindJobStatsQuery {
+  pub top_errors: Option<i64>,
+}
+
+#[get("", wrap = "RequireViewer")]
+pub async fn find_job_stats_route(
+  principal: Principal,
+  pool: Data<Pool>,
+  query: Query<FindJobStatsQuery>,
+) -> Result<HttpResponse, HttpError> {
+  let top_errors = query.top_errors.unwrap_or(5).max(0).min(20);
+  let stats = find_job_stats(&pool.get().await?, top_errors, &principal.scope).await?;
+  Ok(HttpResponse::Ok().json(stats))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindJobTimeseriesQuery {
+  pub from: Option<DateTime<Utc>>,
+  pub to: Option<DateTime<Utc>>,
+  /// Bucket width in seconds
+  pub bucket: Option<i64>,
+  pub task_identifier: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobTimeseries {
+  pub from: DateTime<Utc>,
+  pub to: DateTime<Utc>,
+  pub bucket: i64,
+  pub points: Vec<JobSamplePoint>,
+}
+
+/// Defaults to the last 24 hours by buckets of 5 minutes. Samples are only
+/// recorded by task, so tokens scoped to queues may not read them.
+#[get("/timeseries", wrap = "RequireViewer")]
+pub async fn find_job_timeseries_route(
+  principal: Principal,
+  pool: Data<Pool>,
+  query: Query<FindJobTimeseriesQuery>,
+) -> Result<HttpResponse, HttpError> {
+  if principal.scope.queue_patterns.is_some() {
+    return Err(HttpError::forbidden(
+      "FRBSC",
+      Some(json!({ "message": "Job samples are not recorded by queue" })),
+    ));
+  }
+  let query = query.into_inner();
+  let to = query.to.unwrap_or_else(Utc::now);
+  let from = query.from.unwrap_or(to - Duration::hours(24));
+  let bucket = query.bucket.unwrap_or(300);
+  if bucket < 1 || from >= to || (to - from).num_seconds() / bucket > MAX_TIMESERIES_BUCKETS {
+    return Err(HttpError::bad_request(
+      "IVTSR",
+      Some(json!({
+        "message": "from must precede to, with at most 1000 buckets of at least 1 second"
+      })),
+    ));
+  }
+
+  let points = find_job_sample_points(
+    &pool.get().await?,
+    from,
+    to,
+    bucket,
+    query.task_identifier,
+    principal.scope.task_patterns.as_deref(),
+  )
+  .await?;
+  Ok(HttpResponse::Ok().json(JobTimeseries {
+    from,
+    to,
+    bucket,
+    points,
+  }))
+}
@@ -0,0 +1,24 @@
+use crate::{
+  errors::HttpError,
+  middlewares::{Principal, RequireViewer},
+  repositories::find_workers,
+};
+use actix_web::{
+  get,
+  web::{scope, Data},
+  HttpResponse, Scope,
+};
+use deadpool_postgres::Pool;
+
+pub fn workers_service() -> Scope {
+  scope("/workers").service(find_workers_route)
+}
+
+#[get("", wrap = "RequireViewer")]
+pub async fn find_workers_route(
+  principal: Principal,
+  pool: Data<Pool>,
+) -> Result<HttpResponse, HttpError> {
+  let workers = find_workers(&pool.get().await?, &principal.scope).await?;
+  Ok(HttpResponse::Ok().json(workers))
+}